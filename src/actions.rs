@@ -0,0 +1,131 @@
+//! Commands that tasks launch when they start.
+//!
+//! A task names its command with `run:"code ~/notes.md"`, or `.ziboxrc` maps
+//! task names to commands, so every `open(url)` can launch a browser:
+//!
+//! ```toml
+//! [run]
+//! allow = ["code", "xdg-open"]
+//! commands = { open = "xdg-open {1}" }
+//! ```
+//!
+//! `{1}`, `{2}`, ... stand for the task's parameters, `{}` for all of them
+//! and a leading `~/` for the home directory. Commands are split into words
+//! like a shell would, but never run through one, so there are no pipes,
+//! redirections or expansions to worry about. Only programs on the
+//! allow-list run; it's empty by default. A bare name on the list allows that
+//! name, looked up on the PATH, and nothing else: `/tmp/code` needs its own
+//! entry even when `code` is allowed.
+
+use crate::ir::IRTask;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct Actions {
+    /// Programs that may be run, as written in the command: a bare name
+    /// (found on the PATH) or a path
+    pub allow: Vec<String>,
+    /// Commands for tasks without `run:`, by task name
+    pub commands: HashMap<String, String>,
+}
+
+impl Actions {
+    /// The command a task launches, as program and arguments. `None` when
+    /// it has none, an error when it can't or mustn't run.
+    pub fn command(&self, task: &IRTask) -> Option<Result<Vec<String>>> {
+        let template = task.run.as_ref().or_else(|| self.commands.get(&task.name))?;
+        Some(self.resolve(template, &task.params))
+    }
+
+    fn resolve(&self, template: &str, params: &[String]) -> Result<Vec<String>> {
+        let mut words = Vec::new();
+        for word in split_words(template)? {
+            if word == "{}" {
+                words.extend(params.iter().cloned());
+            } else {
+                words.push(substitute(&expand_home(word), params)?);
+            }
+        }
+
+        let program = words.first().ok_or_else(|| anyhow!("Empty command"))?;
+        if !self.allows(program) {
+            return Err(anyhow!("{} is not in the allow-list ([run] allow in .ziboxrc)", program));
+        }
+        Ok(words)
+    }
+
+    fn allows(&self, program: &str) -> bool {
+        self.allow.iter().any(|allowed| allowed == program)
+    }
+}
+
+fn expand_home(word: String) -> String {
+    match (word.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => word,
+    }
+}
+
+/// Replace `{1}`, `{2}`, ... in a word with the task's parameters
+fn substitute(word: &str, params: &[String]) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = word;
+    while let Some(open) = rest.find('{') {
+        result.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find('}').map(|close| (close, after[..close].parse::<usize>())) {
+            Some((close, Ok(n))) => {
+                let param = n.checked_sub(1).and_then(|i| params.get(i))
+                    .ok_or_else(|| anyhow!("The command uses {{{}}}, but the task has {} parameter(s)", n, params.len()))?;
+                result.push_str(param);
+                rest = &after[close + 1..];
+            },
+            _ => {
+                result.push('{');
+                rest = after;
+            },
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Split a command into words the way a shell would: whitespace separates
+/// them, quotes group them and a backslash escapes the next character
+/// (except inside single quotes)
+pub fn split_words(command: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(anyhow!("Unclosed ' in command: {}", command)),
+                    }
+                }
+            },
+            '"' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => current.extend(chars.next()),
+                        Some(c) => current.push(c),
+                        None => return Err(anyhow!("Unclosed \" in command: {}", command)),
+                    }
+                }
+            },
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
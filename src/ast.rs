@@ -1,3 +1,4 @@
+use chrono::NaiveDate;
 use std::str::FromStr;
 use std::collections::HashSet;
 use serde::{Serialize, Deserialize};
@@ -15,7 +16,7 @@ impl TaskDuration {
     pub fn to_human_string(&self) -> String {
         if self.minutes < 60 {
             format!("{}m", self.minutes)
-        } else if self.minutes % 60 == 0 {
+        } else if self.minutes.is_multiple_of(60) {
             format!("{}h", self.minutes / 60)
         } else {
             format!("{}h {}m", self.minutes / 60, self.minutes % 60)
@@ -40,20 +41,15 @@ impl FromStr for TaskDuration {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Priority {
     Low,
+    #[default]
     Medium,
     High,
     Critical,
 }
 
-impl Default for Priority {
-    fn default() -> Self {
-        Priority::Medium
-    }
-}
-
 impl FromStr for Priority {
     type Err = ();
 
@@ -76,6 +72,14 @@ pub struct Task {
     pub tags: HashSet<String>,
     pub priority: Priority,
     pub depends_on: Vec<String>,
+    pub subtasks: Vec<Task>,
+    pub notes: Vec<String>,
+    pub link: Option<String>,
+    /// The day the task is due (`due:YYYY-MM-DD`)
+    pub due: Option<NaiveDate>,
+    /// Command to launch when the task starts (`run:"..."`)
+    pub run: Option<String>,
+    pub label: Option<String>,
 }
 
 impl Task {
@@ -87,6 +91,12 @@ impl Task {
             tags: HashSet::new(),
             priority: Priority::default(),
             depends_on: Vec::new(),
+            subtasks: Vec::new(),
+            notes: Vec::new(),
+            link: None,
+            due: None,
+            run: None,
+            label: None,
         }
     }
     
@@ -115,9 +125,58 @@ impl Task {
         self
     }
     
+    pub fn with_subtasks(mut self, subtasks: Vec<Task>) -> Self {
+        self.subtasks = subtasks;
+        self
+    }
+    
+    pub fn add_subtask(&mut self, task: Task) {
+        self.subtasks.push(task);
+    }
+    
+    pub fn with_link(mut self, link: Option<String>) -> Self {
+        self.link = link;
+        self
+    }
+    
+    pub fn with_due(mut self, due: Option<NaiveDate>) -> Self {
+        self.due = due;
+        self
+    }
+    
+    pub fn with_run(mut self, run: Option<String>) -> Self {
+        self.run = run;
+        self
+    }
+    
+    pub fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+    
+    pub fn add_note(&mut self, note: String) {
+        self.notes.push(note);
+    }
+    
     pub fn has_tag(&self, tag: &str) -> bool {
         self.tags.contains(tag)
     }
+    
+    /// The task's own duration, or the sum of its subtasks' when omitted
+    pub fn effective_duration(&self) -> Option<TaskDuration> {
+        if self.duration.is_some() || self.subtasks.is_empty() {
+            return self.duration.clone();
+        }
+        
+        let child_durations: Vec<_> = self.subtasks.iter()
+            .filter_map(|t| t.effective_duration())
+            .collect();
+        if child_durations.is_empty() {
+            None
+        } else {
+            Some(TaskDuration::from_minutes(child_durations.iter().map(|d| d.minutes).sum()))
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -143,3 +202,24 @@ impl Block {
         self.tasks.push(task);
     }
 }
+
+/// A top-level `set key = value` directive that overrides configuration
+/// from inside the .zbx file itself.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+}
+
+impl Setting {
+    pub fn new(key: String, value: String) -> Self {
+        Self { key, value }
+    }
+}
+
+/// A whole parsed .zbx file: its blocks plus any file-level settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Program {
+    pub blocks: Vec<Block>,
+    pub settings: Vec<Setting>,
+}
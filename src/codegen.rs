@@ -1,116 +1,133 @@
+use crate::actions::Actions;
 use crate::ir::{IRProgram, IRTask};
-use chrono::{DateTime, Local, Timelike, Datelike};
-use icalendar::{Calendar, Component, Event, EventLike};
+use chrono::{Local, Timelike};
 use std::fmt::Write;
-use anyhow::{Result, anyhow};
-use colored::Colorize;
+use anyhow::Result;
+
+mod calendar;
+mod chart;
+mod cron;
+mod csv;
+mod dot;
+mod gantt;
+mod html;
+pub mod json;
+mod org;
+mod shell;
+mod systemd;
+mod template;
+pub mod taskwarrior;
+mod todotxt;
+mod zbx;
+
+pub use calendar::{CalendarOptions, CalendarTime};
+pub use chart::render_chart;
+pub use json::{json_schema, JSON_SCHEMA_VERSION};
+pub use systemd::{write_systemd_units, SystemdUnits};
 
 pub enum OutputFormat {
-    Shell,
+    /// A bash script, with the commands tasks may launch
+    Shell(Actions),
+    /// A strict POSIX sh script, for dash, busybox and the like
+    Posix(Actions),
+    /// A PowerShell script
+    PowerShell(Actions),
     Markdown,
     Json,
-    Calendar,
+    /// iCalendar, with how to write times, to-dos and reminders
+    Calendar(CalendarOptions),
+    /// .zbx source, optionally annotated with scheduled times and completion
+    Zbx { annotate: bool },
+    TodoTxt,
+    Taskwarrior,
+    Org,
+    /// CSV, or TSV with a tab delimiter
+    Csv { delimiter: u8 },
+    Html,
+    Mermaid,
+    PlantUml,
+    Dot,
+    /// systemd user timers and services, one pair per scheduled task
+    Systemd,
+    /// Crontab lines
+    Cron,
+    /// A user's template, by its source
+    Template(String),
 }
 
 impl OutputFormat {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
-            "shell" | "sh" => Some(OutputFormat::Shell),
+            "shell" | "sh" => Some(OutputFormat::Shell(Actions::default())),
+            "posix" => Some(OutputFormat::Posix(Actions::default())),
+            "powershell" | "pwsh" | "ps1" => Some(OutputFormat::PowerShell(Actions::default())),
             "markdown" | "md" => Some(OutputFormat::Markdown),
             "json" => Some(OutputFormat::Json),
-            "calendar" | "ics" => Some(OutputFormat::Calendar),
+            "calendar" | "ics" => Some(OutputFormat::Calendar(CalendarOptions::default())),
+            "zbx" | "zibox" => Some(OutputFormat::Zbx { annotate: false }),
+            "todotxt" | "todo.txt" => Some(OutputFormat::TodoTxt),
+            "taskwarrior" | "tw" => Some(OutputFormat::Taskwarrior),
+            "org" | "orgmode" | "org-mode" => Some(OutputFormat::Org),
+            "csv" => Some(OutputFormat::Csv { delimiter: b',' }),
+            "tsv" => Some(OutputFormat::Csv { delimiter: b'\t' }),
+            "html" | "htm" => Some(OutputFormat::Html),
+            "mermaid" | "mmd" => Some(OutputFormat::Mermaid),
+            "plantuml" | "puml" => Some(OutputFormat::PlantUml),
+            "dot" | "graphviz" | "gv" => Some(OutputFormat::Dot),
+            "systemd" | "timers" => Some(OutputFormat::Systemd),
+            "cron" | "crontab" => Some(OutputFormat::Cron),
+            "template" => Some(OutputFormat::Template(String::new())),
             _ => None,
         }
     }
     
     pub fn extension(&self) -> &'static str {
         match self {
-            OutputFormat::Shell => "sh",
+            OutputFormat::Shell(_) => "sh",
+            OutputFormat::Posix(_) => "sh",
+            OutputFormat::PowerShell(_) => "ps1",
             OutputFormat::Markdown => "md",
             OutputFormat::Json => "json",
-            OutputFormat::Calendar => "ics",
+            OutputFormat::Calendar(_) => "ics",
+            OutputFormat::Zbx { .. } => "zbx",
+            OutputFormat::TodoTxt => "txt",
+            OutputFormat::Taskwarrior => "json",
+            OutputFormat::Org => "org",
+            OutputFormat::Csv { delimiter: b'\t' } => "tsv",
+            OutputFormat::Csv { .. } => "csv",
+            OutputFormat::Html => "html",
+            OutputFormat::Mermaid => "mmd",
+            OutputFormat::PlantUml => "puml",
+            OutputFormat::Dot => "dot",
+            OutputFormat::Systemd => "service",
+            OutputFormat::Cron => "cron",
+            OutputFormat::Template(_) => "txt",
         }
     }
 }
 
 pub fn generate_output(program: &IRProgram, format: OutputFormat) -> Result<String> {
     match format {
-        OutputFormat::Shell => emit_shell_script(program),
+        OutputFormat::Shell(actions) => shell::emit_shell_script(program, &actions),
+        OutputFormat::Posix(actions) => shell::emit_posix_script(program, &actions),
+        OutputFormat::PowerShell(actions) => shell::emit_powershell_script(program, &actions),
         OutputFormat::Markdown => emit_markdown(program),
-        OutputFormat::Json => emit_json(program),
-        OutputFormat::Calendar => emit_calendar(program),
-    }
-}
-
-fn emit_shell_script(program: &IRProgram) -> Result<String> {
-    let mut output = String::new();
-    
-    // Shell script header
-    writeln!(&mut output, "#!/bin/bash")?;
-    writeln!(&mut output, "# Generated by Zero Inbox")?;
-    writeln!(&mut output, "# Date: {}", Local::now().format("%Y-%m-%d %H:%M:%S"))?;
-    writeln!(&mut output, "")?;
-    
-    // Function to check if notify-send is available
-    writeln!(&mut output, "# Check if notification tools are available")?;
-    writeln!(&mut output, "HAS_NOTIFY=false")?;
-    writeln!(&mut output, "if command -v notify-send &> /dev/null; then")?;
-    writeln!(&mut output, "    HAS_NOTIFY=true")?;
-    writeln!(&mut output, "elif command -v osascript &> /dev/null; then")?;
-    writeln!(&mut output, "    HAS_NOTIFY=true")?;
-    writeln!(&mut output, "fi")?;
-    writeln!(&mut output, "")?;
-    
-    // Function to send notifications
-    writeln!(&mut output, "function notify() {{")?;
-    writeln!(&mut output, "    if [ \"$HAS_NOTIFY\" = true ]; then")?;
-    writeln!(&mut output, "        if command -v notify-send &> /dev/null; then")?;
-    writeln!(&mut output, "            notify-send \"$1\" \"$2\"")?;
-    writeln!(&mut output, "        elif command -v osascript &> /dev/null; then")?;
-    writeln!(&mut output, "            osascript -e \"display notification \\\"$2\\\" with title \\\"$1\\\"\"")?;
-    writeln!(&mut output, "        fi")?;
-    writeln!(&mut output, "    else")?;
-    writeln!(&mut output, "        echo \"$1: $2\"")?;
-    writeln!(&mut output, "    fi")?;
-    writeln!(&mut output, "}}")?;
-    writeln!(&mut output, "")?;
-    
-    // Generate tasks
-    for block in &program.blocks {
-        writeln!(&mut output, "echo \"[{}]\"", block.name)?;
-        
-        for task in &block.tasks {
-            let task_name = task.display_name();
-            let duration = task.duration_minutes();
-            
-            // Format the start time if available
-            let start_time = if let Some(start) = task.scheduled_start {
-                start.format("%H:%M").to_string()
-            } else {
-                "??:??".to_string()
-            };
-            
-            writeln!(&mut output, "# Task: {} ({})", task_name, format_duration(duration))?;
-            writeln!(&mut output, "echo \"[{}] Starting: {}\"", start_time, task_name)?;
-            writeln!(&mut output, "notify \"Task Start\" \"{}\"", task_name)?;
-            
-            // If the task has a duration, add a sleep command
-            if duration > 0 {
-                writeln!(&mut output, "# Sleep for {} seconds", duration * 60)?;
-                writeln!(&mut output, "echo \"Waiting for {} minutes...\"", duration)?;
-                writeln!(&mut output, "sleep {}", duration * 60)?;
-            }
-            
-            writeln!(&mut output, "echo \"Completed: {}\"", task_name)?;
-            writeln!(&mut output, "notify \"Task Complete\" \"{}\"", task_name)?;
-            writeln!(&mut output, "")?;
-        }
+        OutputFormat::Json => json::emit_json(program),
+        OutputFormat::Calendar(options) => calendar::emit_calendar(program, &options),
+        OutputFormat::Zbx { annotate } => zbx::emit_zbx(program, annotate),
+        OutputFormat::TodoTxt => todotxt::emit_todotxt(program),
+        OutputFormat::Taskwarrior => taskwarrior::emit_taskwarrior(program),
+        OutputFormat::Org => org::emit_org(program),
+        OutputFormat::Csv { delimiter } => csv::emit_csv(program, delimiter),
+        OutputFormat::Html => html::emit_html(program),
+        OutputFormat::Mermaid => gantt::emit_mermaid(program),
+        OutputFormat::PlantUml => gantt::emit_plantuml(program),
+        OutputFormat::Dot => dot::emit_dot(program),
+        OutputFormat::Systemd => systemd::emit_systemd(program),
+        OutputFormat::Cron => cron::emit_cron(program),
+        OutputFormat::Template(source) => template::render_template(program, &source),
     }
-    
-    writeln!(&mut output, "echo \"All tasks completed!\"")?;
-    writeln!(&mut output, "notify \"Zero Inbox\" \"All tasks completed!\"")?;
-    
-    Ok(output)
 }
 
 fn emit_markdown(program: &IRProgram) -> Result<String> {
@@ -118,17 +135,17 @@ fn emit_markdown(program: &IRProgram) -> Result<String> {
     
     // Markdown header
     writeln!(&mut output, "# Zero Inbox Plan")?;
-    writeln!(&mut output, "")?;
+    writeln!(&mut output)?;
     writeln!(&mut output, "Generated on: {}", Local::now().format("%Y-%m-%d %H:%M:%S"))?;
-    writeln!(&mut output, "")?;
+    writeln!(&mut output)?;
     
     // Summary
     writeln!(&mut output, "## Summary")?;
-    writeln!(&mut output, "")?;
+    writeln!(&mut output)?;
     writeln!(&mut output, "- Total blocks: {}", program.blocks.len())?;
     writeln!(&mut output, "- Total tasks: {}", program.all_tasks().len())?;
     writeln!(&mut output, "- Total duration: {}", format_duration(program.total_duration()))?;
-    writeln!(&mut output, "")?;
+    writeln!(&mut output)?;
     
     // Generate tasks by block
     for block in &program.blocks {
@@ -145,118 +162,114 @@ fn emit_markdown(program: &IRProgram) -> Result<String> {
         // Subtle blue color for block headers (ANSI, will be ignored in plain Markdown)
         let block_header = format!("## {}{}", block_icon, block.name);
         writeln!(&mut output, "{}", block_header)?;
-        writeln!(&mut output, "")?;
+        writeln!(&mut output)?;
         
         for task in &block.tasks {
-            let task_name = task.display_name();
-            let duration = task.duration_minutes();
-            
-            // Format the start time if available
-            let time_info = if let Some(start) = task.scheduled_start {
-                format!("{}", start.format("%H:%M"))
-            } else {
-                "".to_string()
-            };
-            
-            let duration_str = if duration > 0 {
-                format!("({})", format_duration(duration))
-            } else {
-                "".to_string()
-            };
-            
-            let priority_marker = match task.priority {
-                crate::ast::Priority::Low => "",
-                crate::ast::Priority::Medium => "",
-                crate::ast::Priority::High => "\x1b[33m⭐\x1b[0m ", // yellow
-                crate::ast::Priority::Critical => "\x1b[31m🔥\x1b[0m ", // red
-            };
-            
-            // Fun tag icons for common tags, with color
-            let tag_icons = |tag: &str| match tag.to_lowercase().as_str() {
-                "deepwork" => "🧠",
-                "admin" => "🗂️",
-                "health" => "🏃",
-                "learning" => "📚",
-                "collaboration" => "🤝",
-                "meeting" => "📅",
-                "focus" => "🎯",
-                "break" => "☕",
-                _ => "",
-            };
-            let tags = if !task.tags.is_empty() {
-                let tag_list: Vec<_> = task.tags.iter()
-                    .map(|t| {
-                        let icon = tag_icons(t);
-                        let colored_tag = format!("\x1b[36m`#{}`\x1b[0m", t); // cyan
-                        if icon.is_empty() {
-                            colored_tag
-                        } else {
-                            format!("{} {}", icon, colored_tag)
-                        }
-                    })
-                    .collect();
-                format!(" {}", tag_list.join(" "))
-            } else {
-                "".to_string()
-            };
-            
-            // Format dependencies
-            let deps = if !task.depends_on.is_empty() {
-                format!(" (depends on: {})", task.depends_on.join(", "))
-            } else {
-                "".to_string()
-            };
-            
-            let time_prefix = if !time_info.is_empty() { format!("[{}] ", time_info) } else { String::new() };
-            // Subtle green checkmark for completed tasks
-            let completed = if task.completed { " ✅" } else { "" };
-            writeln!(
-                &mut output, 
-                "- {} {}**{}** {} {}{}{}",
-                time_prefix,
-                priority_marker,
-                task_name,
-                duration_str,
-                tags,
-                deps,
-                completed
-            )?;
+            write_markdown_task(&mut output, program, task, 0)?;
         }
         
-        writeln!(&mut output, "")?;
+        writeln!(&mut output)?;
     }
     
     Ok(output)
 }
 
-fn emit_json(program: &IRProgram) -> Result<String> {
-    serde_json::to_string_pretty(program).map_err(|e| anyhow!("JSON serialization error: {}", e))
-}
-
-fn emit_calendar(program: &IRProgram) -> Result<String> {
-    let mut calendar = Calendar::new();
+/// Write a task as a checklist item, nesting its subtasks beneath it
+fn write_markdown_task(output: &mut String, program: &IRProgram, task: &IRTask, depth: usize) -> Result<()> {
+    let task_name = task.display_name();
+    let duration = task.duration_minutes();
     
-    for block in &program.blocks {
-        for task in &block.tasks {
-            // Skip tasks without scheduled times
-            if task.scheduled_start.is_none() || task.scheduled_end.is_none() {
-                continue;
-            }
-            
-            let start = task.scheduled_start.unwrap().with_timezone(&chrono::Utc);
-            let end = task.scheduled_end.unwrap().with_timezone(&chrono::Utc);
-            
-            let mut event = Event::new();
-            event.summary(&task.display_name());
-            event.description(&format!("Block: {}", block.name));
-            event.starts(start);
-            event.ends(end);
-            // Note: icalendar::Event does not support add_category in this version, so we skip adding categories.
-            calendar.push(event);
-        }
+    // Format the start time if available
+    let time_info = if let Some(start) = task.scheduled_start {
+        format!("{}", start.format("%H:%M"))
+    } else {
+        "".to_string()
+    };
+    
+    let duration_str = if duration > 0 {
+        format!("({})", format_duration(duration))
+    } else {
+        "".to_string()
+    };
+    
+    let priority_marker = match task.priority {
+        crate::ast::Priority::Low => "",
+        crate::ast::Priority::Medium => "",
+        crate::ast::Priority::High => "\x1b[33m⭐\x1b[0m ", // yellow
+        crate::ast::Priority::Critical => "\x1b[31m🔥\x1b[0m ", // red
+    };
+    
+    // Fun tag icons for common tags, with color
+    let tag_icons = |tag: &str| match tag.to_lowercase().as_str() {
+        "deepwork" => "🧠",
+        "admin" => "🗂️",
+        "health" => "🏃",
+        "learning" => "📚",
+        "collaboration" => "🤝",
+        "meeting" => "📅",
+        "focus" => "🎯",
+        "break" => "☕",
+        _ => "",
+    };
+    let tags = if !task.tags.is_empty() {
+        let tag_list: Vec<_> = task.tags.iter()
+            .map(|t| {
+                let icon = tag_icons(t);
+                let colored_tag = format!("\x1b[36m`#{}`\x1b[0m", t); // cyan
+                if icon.is_empty() {
+                    colored_tag
+                } else {
+                    format!("{} {}", icon, colored_tag)
+                }
+            })
+            .collect();
+        format!(" {}", tag_list.join(" "))
+    } else {
+        "".to_string()
+    };
+    
+    // Format dependencies
+    let deps = if !task.depends_on.is_empty() {
+        let dep_names: Vec<_> = task.depends_on.iter()
+            .map(|dep| program.get_task(dep).map_or_else(|| dep.clone(), |t| t.display_name()))
+            .collect();
+        format!(" (depends on: {})", dep_names.join(", "))
+    } else {
+        "".to_string()
+    };
+    
+    let time_prefix = if !time_info.is_empty() { format!("[{}] ", time_info) } else { String::new() };
+    // Completed tasks get a ticked checkbox
+    let checkbox = if task.completed { "x" } else { " " };
+    writeln!(
+        output,
+        "{}- [{}] {}{}**{}** {} {}{}",
+        "  ".repeat(depth),
+        checkbox,
+        time_prefix,
+        priority_marker,
+        task_name,
+        duration_str,
+        tags,
+        deps
+    )?;
+    
+    // Notes and links render as sub-bullets, continuation lines aligned under the text
+    let detail_indent = "  ".repeat(depth + 1);
+    for note in &task.notes {
+        let note = note.replace('\n', &format!("\n{}  ", detail_indent));
+        writeln!(output, "{}- {}", detail_indent, note)?;
+    }
+    if let Some(link) = &task.link {
+        writeln!(output, "{}- 🔗 <{}>", detail_indent, link)?;
     }
     
-    Ok(calendar.to_string())
+    // Subtasks render as a nested checklist
+    for subtask in &task.subtasks {
+        write_markdown_task(output, program, subtask, depth + 1)?;
+    }
+    
+    Ok(())
 }
 
 pub fn visualize_schedule(program: &IRProgram) -> Result<String> {
@@ -265,7 +278,7 @@ pub fn visualize_schedule(program: &IRProgram) -> Result<String> {
     // Header
     writeln!(&mut output, "Schedule Visualization")?;
     writeln!(&mut output, "=====================")?;
-    writeln!(&mut output, "")?;
+    writeln!(&mut output)?;
     
     // Get the earliest and latest times
     let mut earliest = Local::now();
@@ -336,9 +349,27 @@ pub fn visualize_schedule(program: &IRProgram) -> Result<String> {
 fn format_duration(minutes: u64) -> String {
     if minutes < 60 {
         format!("{}m", minutes)
-    } else if minutes % 60 == 0 {
+    } else if minutes.is_multiple_of(60) {
         format!("{}h", minutes / 60)
     } else {
         format!("{}h {}m", minutes / 60, minutes % 60)
     }
-}
\ No newline at end of file
+}
+
+/// `name(params)` as written in source, without the space `display_name` adds
+fn source_name(task: &IRTask) -> String {
+    if task.params.is_empty() {
+        task.name.clone()
+    } else {
+        format!("{}({})", task.name, task.params.join(","))
+    }
+}
+
+/// A duration the parser accepts: whole hours as `2h`, anything else in minutes
+fn duration_literal(minutes: u64) -> String {
+    if minutes > 0 && minutes.is_multiple_of(60) {
+        format!("{}h", minutes / 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
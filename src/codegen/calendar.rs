@@ -0,0 +1,227 @@
+//! iCalendar (.ics) output.
+//!
+//! Each scheduled task becomes a VEVENT whose UID comes from the task ID and
+//! day, so importing the file again updates events instead of duplicating
+//! them. Tags become CATEGORIES, priorities map onto PRIORITY, and reminders
+//! can be set per priority. Unscheduled tasks can go along as VTODOs.
+//! Subtasks share their parent's slot, so rather than overlapping it they're
+//! listed in its DESCRIPTION. Zoned times come with a VTIMEZONE giving the
+//! zone's offsets over the plan, so the file doesn't rely on the reader
+//! knowing the zone's name.
+
+use crate::ast::Priority;
+use crate::ir::{IRBlock, IRProgram, IRTask};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc};
+use icalendar::{
+    Alarm, Calendar, CalendarDateTime, Component, DatePerhapsTime, Event, EventLike, Todo, TodoStatus, Trigger,
+};
+
+/// How event times are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CalendarTime {
+    /// In UTC, the same instant wherever the file is opened
+    #[default]
+    Utc,
+    /// Wall-clock time with no zone, so 09:00 stays 09:00 when travelling
+    Floating,
+    /// Wall-clock time with this machine's time zone as TZID
+    Zoned,
+}
+
+impl CalendarTime {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "utc" => Some(CalendarTime::Utc),
+            "floating" | "local" => Some(CalendarTime::Floating),
+            "zoned" | "tzid" => Some(CalendarTime::Zoned),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CalendarOptions {
+    pub time: CalendarTime,
+    /// Emit unscheduled tasks as VTODO items
+    pub todos: bool,
+    /// Minutes before the start to remind, by priority
+    pub alarms: Vec<(Priority, u32)>,
+}
+
+pub fn emit_calendar(program: &IRProgram, options: &CalendarOptions) -> Result<String> {
+    let zone = match options.time {
+        CalendarTime::Zoned => Some(
+            iana_time_zone::get_timezone()
+                .map_err(|e| anyhow!("Could not find the system time zone ({}); use utc or floating time", e))?,
+        ),
+        _ => None,
+    };
+    let time = |time: DateTime<Local>| -> DatePerhapsTime {
+        match (options.time, &zone) {
+            (CalendarTime::Floating, _) => time.naive_local().into(),
+            (CalendarTime::Zoned, Some(tzid)) => {
+                CalendarDateTime::WithTimezone { date_time: time.naive_local(), tzid: tzid.clone() }.into()
+            },
+            _ => time.with_timezone(&Utc).into(),
+        }
+    };
+    // One stamp for the whole file, so every entry agrees on when it was made
+    let stamp = Utc::now();
+    let mut times = Vec::new();
+
+    let mut calendar = Calendar::new();
+    for block in &program.blocks {
+        for task in &block.tasks {
+            match (task.scheduled_start, task.scheduled_end) {
+                (Some(start), Some(end)) => {
+                    // Task IDs are stable across edits, so re-importing the file
+                    // updates events instead of duplicating them
+                    let mut event = Event::new();
+                    event.uid(&format!("{}-{}@zero-inbox", task.id, start.format("%Y%m%d")));
+                    event.timestamp(stamp);
+                    describe(&mut event, block, task);
+                    event.starts(time(start));
+                    event.ends(time(end));
+                    times.extend([start, end]);
+                    if let Some(&(_, minutes)) = options.alarms.iter().find(|(p, _)| *p == task.priority) {
+                        event.alarm(Alarm::display(
+                            &task.display_name(),
+                            Trigger::before_start(chrono::Duration::minutes(minutes as i64)),
+                        ));
+                    }
+                    calendar.push(event);
+                },
+                _ if options.todos => {
+                    let mut todo = Todo::new();
+                    todo.uid(&format!("{}@zero-inbox", task.id));
+                    todo.timestamp(stamp);
+                    describe(&mut todo, block, task);
+                    if task.completed {
+                        todo.status(TodoStatus::Completed);
+                        todo.percent_complete(100);
+                    } else {
+                        todo.status(TodoStatus::NeedsAction);
+                    }
+                    calendar.push(todo);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    let mut output = calendar.to_string();
+    if let (Some(tzid), false) = (&zone, times.is_empty()) {
+        // Definitions go before the entries that use them
+        let at = output.find("\r\nBEGIN:")
+            .map(|i| i + 2)
+            .or_else(|| output.find("END:VCALENDAR"))
+            .unwrap_or(output.len());
+        output.insert_str(at, &timezone(tzid, times));
+    }
+    Ok(output)
+}
+
+/// A VTIMEZONE with the offset in effect at the first of `times` and every
+/// change of offset up to the last, which is all a reader needs for them
+fn timezone(tzid: &str, mut times: Vec<DateTime<Local>>) -> String {
+    times.sort();
+    let offset = |seconds: i64| match Local.timestamp_opt(seconds, 0) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.offset().local_minus_utc(),
+        LocalResult::None => 0,
+    };
+
+    let first = times[0].offset().local_minus_utc();
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap().and_time(NaiveTime::MIN);
+    // (local start, offset from, offset to)
+    let mut observances = vec![(epoch, first, first)];
+    for pair in times.windows(2) {
+        let (mut before, mut after) = (pair[0].timestamp(), pair[1].timestamp());
+        let (from, to) = (offset(before), offset(after));
+        if from == to {
+            continue;
+        }
+        while after - before > 1 {
+            let middle = before + (after - before) / 2;
+            if offset(middle) == from {
+                before = middle;
+            } else {
+                after = middle;
+            }
+        }
+        let start = DateTime::from_timestamp(after + from as i64, 0).unwrap_or_default().naive_utc();
+        observances.push((start, from, to));
+    }
+
+    let lowest = observances.iter().map(|&(_, _, to)| to).min().unwrap_or(first);
+    let mut lines = vec!["BEGIN:VTIMEZONE".to_string(), format!("TZID:{}", tzid)];
+    for (start, from, to) in observances {
+        let kind = if to > lowest { "DAYLIGHT" } else { "STANDARD" };
+        lines.push(format!("BEGIN:{}", kind));
+        lines.push(format!("DTSTART:{}", start.format("%Y%m%dT%H%M%S")));
+        lines.push(format!("TZOFFSETFROM:{}", utc_offset(from)));
+        lines.push(format!("TZOFFSETTO:{}", utc_offset(to)));
+        lines.push(format!("END:{}", kind));
+    }
+    lines.push("END:VTIMEZONE".to_string());
+    lines.iter().map(|line| format!("{}\r\n", line)).collect()
+}
+
+/// `+0200`, as iCalendar writes offsets
+fn utc_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// What events and to-dos have in common
+fn describe<C: Component>(component: &mut C, block: &IRBlock, task: &IRTask) {
+    component.summary(&task.display_name());
+    let mut description = format!("Block: {}", block.name);
+    if !task.subtasks.is_empty() {
+        description.push_str("\n\nSubtasks:");
+        list_subtasks(&mut description, task, 0);
+    }
+    if !task.notes.is_empty() {
+        description.push_str("\n\n");
+        description.push_str(&task.notes.join("\n\n"));
+    }
+    component.description(&description);
+    if let Some(link) = &task.link {
+        component.url(link);
+    }
+    component.priority(priority(&task.priority));
+    if !task.tags.is_empty() {
+        let mut tags: Vec<&str> = task.tags.iter().map(String::as_str).collect();
+        tags.sort();
+        component.add_property("CATEGORIES", &tags.join(","));
+    }
+}
+
+/// `- 09:00-09:30 outline (done)`, nested subtasks indented below
+fn list_subtasks(description: &mut String, task: &IRTask, depth: usize) {
+    for subtask in &task.subtasks {
+        description.push('\n');
+        description.push_str(&"  ".repeat(depth));
+        description.push_str("- ");
+        if let (Some(start), Some(end)) = (subtask.scheduled_start, subtask.scheduled_end) {
+            description.push_str(&format!("{}-{} ", start.format("%H:%M"), end.format("%H:%M")));
+        }
+        description.push_str(&subtask.display_name());
+        if subtask.completed {
+            description.push_str(" (done)");
+        }
+        list_subtasks(description, subtask, depth + 1);
+    }
+}
+
+/// iCalendar priorities run from 1 (highest) to 9 (lowest)
+fn priority(priority: &Priority) -> u32 {
+    match priority {
+        Priority::Critical => 1,
+        Priority::High => 3,
+        Priority::Medium => 5,
+        Priority::Low => 9,
+    }
+}
@@ -0,0 +1,211 @@
+//! Gantt charts of a scheduled plan, as SVG or PNG.
+//!
+//! Each scheduled task gets a row, subtasks marked `›` under their parent, and
+//! each block a colour: a light band behind its rows and solid bars for its
+//! tasks. A parent is drawn as a thin summary bar over its subtasks, done
+//! tasks are faded, arrows run from each dependency to the task waiting on
+//! it, and a red line marks the current time when it falls on the chart.
+//! A task with a due date gets a diamond at the end of that day on its row,
+//! pinned to the edge when the day is off the chart, and red when the task
+//! is scheduled to finish after it.
+
+use crate::ir::{IRProgram, IRTask};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Local, Timelike};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::collections::HashMap;
+use std::path::Path;
+
+const WIDTH: u32 = 1200;
+const ROW_HEIGHT: u32 = 28;
+const FONT: &str = "sans-serif";
+
+struct Row<'a> {
+    task: &'a IRTask,
+    depth: usize,
+    block: usize,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+}
+
+/// Draw the chart to `path`; the extension picks SVG or PNG
+pub fn render_chart(program: &IRProgram, path: &Path) -> Result<()> {
+    let rows = rows(program);
+    if rows.is_empty() {
+        return Err(anyhow!("Nothing to chart: no task is scheduled"));
+    }
+    let size = (WIDTH, (rows.len() as u32 * ROW_HEIGHT + 110).max(240));
+
+    let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+    match extension.as_deref() {
+        Some("svg") => draw(SVGBackend::new(path, size).into_drawing_area(), program, &rows),
+        Some("png") => draw(BitMapBackend::new(path, size).into_drawing_area(), program, &rows),
+        _ => Err(anyhow!("Unsupported chart format: {} (use .svg or .png)", path.display())),
+    }
+}
+
+/// Scheduled tasks in plan order, each parent followed by its subtasks
+fn rows(program: &IRProgram) -> Vec<Row<'_>> {
+    fn add<'a>(rows: &mut Vec<Row<'a>>, task: &'a IRTask, depth: usize, block: usize) {
+        if let (Some(start), Some(end)) = (task.scheduled_start, task.scheduled_end) {
+            rows.push(Row { task, depth, block, start, end });
+        }
+        for subtask in &task.subtasks {
+            add(rows, subtask, depth + 1, block);
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (block, b) in program.blocks.iter().enumerate() {
+        for task in &b.tasks {
+            add(&mut rows, task, 0, block);
+        }
+    }
+    rows
+}
+
+fn draw<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, program: &IRProgram, rows: &[Row]) -> Result<()> {
+    let error = |e: DrawingAreaErrorKind<DB::ErrorType>| anyhow!("Failed to draw chart: {}", e);
+    root.fill(&WHITE).map_err(error)?;
+
+    // Whole hours either side, so the scale has round numbers
+    let first = rows.iter().map(|r| r.start).min().unwrap();
+    let last = rows.iter().map(|r| r.end).max().unwrap();
+    let from = first.with_minute(0).and_then(|t| t.with_second(0)).unwrap_or(first);
+    let hours = ((last - from).num_minutes() as f64 / 60.0).ceil().max(1.0);
+    let x = |time: DateTime<Local>| (time - from).num_minutes() as f64 / 60.0;
+    // Row 0 at the top; a row's centre is its y value
+    let count = rows.len();
+    let y = |index: usize| (count - 1 - index) as f64;
+
+    // Labels are right-aligned, so subtasks are marked rather than indented
+    let labels: Vec<String> = rows.iter()
+        .map(|r| format!("{}{}", "› ".repeat(r.depth), r.task.display_name()))
+        .collect();
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u32 * 8 + 20;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Zero Inbox Schedule", (FONT, 22))
+        .margin(15)
+        .x_label_area_size(30)
+        .y_label_area_size(label_width.min(320))
+        .build_cartesian_2d(0f64..hours, -0.5f64..count as f64 - 0.5)
+        .map_err(error)?;
+
+    chart.configure_mesh()
+        .x_labels(hours as usize + 1)
+        .x_label_formatter(&|h| (from + Duration::minutes((h * 60.0).round() as i64)).format("%H:%M").to_string())
+        .y_labels(count)
+        .y_label_formatter(&|v| {
+            let index = count as f64 - 1.0 - v.round();
+            labels.get(index as usize).filter(|_| (v - v.round()).abs() < 0.01 && index >= 0.0).cloned().unwrap_or_default()
+        })
+        .disable_y_mesh()
+        .label_style((FONT, 13))
+        .draw()
+        .map_err(error)?;
+
+    // Block bands, one per run of rows in the same block
+    let mut index = 0;
+    while index < count {
+        let block = rows[index].block;
+        let end = rows[index..].iter().position(|r| r.block != block).map_or(count, |n| index + n);
+        let colour = block_colour(block);
+        chart.draw_series([Rectangle::new(
+            [(0.0, y(index) + 0.5), (hours, y(end - 1) - 0.5)],
+            colour.mix(0.1).filled(),
+        )]).map_err(error)?;
+        chart.draw_series([Text::new(
+            program.blocks[block].name.clone(),
+            (hours * 0.005, y(index) + 0.45),
+            (FONT, 12).into_font().color(&colour),
+        )]).map_err(error)?;
+        index = end;
+    }
+
+    // Bars: parents as thin summary bars, done tasks faded
+    for (index, row) in rows.iter().enumerate() {
+        let colour = block_colour(row.block);
+        let opacity = if row.task.completed { 0.35 } else { 0.9 };
+        let half = if row.task.subtasks.is_empty() { 0.32 } else { 0.12 };
+        chart.draw_series([Rectangle::new(
+            [(x(row.start), y(index) + half), (x(row.end), y(index) - half)],
+            colour.mix(opacity).filled(),
+        )]).map_err(error)?;
+    }
+
+    // Dependency arrows, from the end of each dependency to the start of the
+    // task waiting on it
+    let positions: HashMap<&str, usize> = rows.iter().enumerate().map(|(i, r)| (r.task.id.as_str(), i)).collect();
+    let head = hours * 0.006;
+    for (index, row) in rows.iter().enumerate() {
+        for dependency in &row.task.depends_on {
+            let Some(&from_index) = positions.get(dependency.as_str()) else {
+                continue;
+            };
+            let (x0, y0) = (x(rows[from_index].end), y(from_index));
+            let (x1, y1) = (x(row.start), y(index));
+            let style = BLACK.mix(0.6).stroke_width(1);
+            chart.draw_series([PathElement::new(vec![(x0, y0), (x0 + head, y0), (x0 + head, y1), (x1, y1)], style)])
+                .map_err(error)?;
+            chart.draw_series([Polygon::new(
+                vec![(x1, y1), (x1 - head, y1 + 0.15), (x1 - head, y1 - 0.15)],
+                BLACK.mix(0.6).filled(),
+            )]).map_err(error)?;
+        }
+    }
+
+    // Deadlines: the end of the due day, kept on the chart so an early or
+    // late one still shows against its row
+    for (index, row) in rows.iter().enumerate() {
+        let Some(due) = row.task.due else { continue };
+        let Some(deadline) = due.succ_opt()
+            .and_then(|day| day.and_hms_opt(0, 0, 0))
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        else {
+            continue;
+        };
+        let colour = if row.end > deadline { RED } else { RGBColor(66, 66, 66) };
+        let (dx, dy) = (hours * 0.005, 0.25);
+        let at = x(deadline).clamp(dx, hours - dx);
+        chart.draw_series([Polygon::new(
+            vec![(at, y(index) + dy), (at + dx, y(index)), (at, y(index) - dy), (at - dx, y(index))],
+            colour.filled(),
+        )]).map_err(error)?;
+        // The label goes on whichever side has room
+        let (side, offset) = if at > hours / 2.0 { (HPos::Right, -2.0 * dx) } else { (HPos::Left, 2.0 * dx) };
+        chart.draw_series([Text::new(
+            format!("due {}", due.format("%Y-%m-%d")),
+            (at + offset, y(index)),
+            (FONT, 11).into_font().color(&colour).pos(Pos::new(side, VPos::Center)),
+        )]).map_err(error)?;
+    }
+
+    // The current time, if it's on the chart
+    let now = x(Local::now());
+    if (0.0..=hours).contains(&now) {
+        chart.draw_series([PathElement::new(vec![(now, -0.5), (now, count as f64 - 0.5)], RED.stroke_width(2))])
+            .map_err(error)?;
+        chart.draw_series([Text::new("now", (now + hours * 0.004, count as f64 - 0.55), (FONT, 12).into_font().color(&RED))])
+            .map_err(error)?;
+    }
+
+    root.present().map_err(error)?;
+    Ok(())
+}
+
+fn block_colour(block: usize) -> RGBColor {
+    const PALETTE: [RGBColor; 8] = [
+        RGBColor(21, 101, 192),
+        RGBColor(46, 125, 50),
+        RGBColor(239, 108, 0),
+        RGBColor(106, 27, 154),
+        RGBColor(0, 131, 143),
+        RGBColor(198, 40, 40),
+        RGBColor(121, 85, 72),
+        RGBColor(84, 110, 122),
+    ];
+    PALETTE[block % PALETTE.len()]
+}
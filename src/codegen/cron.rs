@@ -0,0 +1,48 @@
+//! Crontab lines that send a notification as each task starts.
+//!
+//! Cron has no year field, so a line fires on the same day every year; the
+//! plan is for one day, so regenerate it rather than keeping it installed.
+//! Names are single-quoted for the shell cron runs each line with, and `%`,
+//! which cron turns into a line break, is escaped.
+
+use super::shell::quote;
+use crate::ir::{IRProgram, IRTask};
+use anyhow::Result;
+use chrono::Local;
+use std::fmt::Write;
+
+pub fn emit_cron(program: &IRProgram) -> Result<String> {
+    let mut output = String::new();
+    writeln!(&mut output, "# Generated by Zero Inbox")?;
+    writeln!(&mut output, "# Date: {}", Local::now().format("%Y-%m-%d %H:%M:%S"))?;
+    writeln!(&mut output, "# Install with: (crontab -l; cat this-file) | crontab -")?;
+
+    let tasks = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .filter(|t| !t.completed);
+    for task in tasks {
+        let Some(start) = task.scheduled_start else { continue };
+        writeln!(&mut output)?;
+        writeln!(&mut output, "# {}", single_line(&task.display_name()))?;
+        writeln!(&mut output, "{} {}", start.format("%-M %-H %-d %-m *"), command(task))?;
+    }
+
+    Ok(output)
+}
+
+/// Cron jobs don't get the desktop's session bus, so it's pointed at the
+/// usual one unless the crontab sets it
+fn command(task: &IRTask) -> String {
+    let text = format!(
+        "DBUS_SESSION_BUS_ADDRESS=\"${{DBUS_SESSION_BUS_ADDRESS:-unix:path=/run/user/$(id -u)/bus}}\" notify-send -- {} {}",
+        quote("Task Start"),
+        quote(&single_line(&task.display_name()))
+    );
+    text.replace('%', "\\%")
+}
+
+/// A crontab entry is one line
+fn single_line(text: &str) -> String {
+    text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
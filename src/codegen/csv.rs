@@ -0,0 +1,65 @@
+//! CSV and TSV, one row per task, for spreadsheets.
+//!
+//! Subtasks get rows of their own after their parent, which the `parent`
+//! column names. Durations are in minutes so a spreadsheet can add them up,
+//! and a parent's is left blank when it's just the sum of its subtasks.
+//! Lists (params, tags, dependencies) are comma-separated, notes are
+//! separated by blank lines, and times are local `YYYY-MM-DD HH:MM`.
+
+use crate::ir::{IRProgram, IRTask};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local};
+
+const COLUMNS: [&str; 14] = [
+    "id", "block", "name", "params", "duration", "priority", "tags", "deps", "start", "end", "completed",
+    "parent", "link", "notes",
+];
+
+pub fn emit_csv(program: &IRProgram, delimiter: u8) -> Result<String> {
+    let mut writer = ::csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
+    writer.write_record(COLUMNS)?;
+
+    for block in &program.blocks {
+        for task in &block.tasks {
+            write_task(&mut writer, task, None)?;
+        }
+    }
+
+    let bytes = writer.into_inner().map_err(|e| anyhow!("CSV error: {}", e))?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn write_task(writer: &mut ::csv::Writer<Vec<u8>>, task: &IRTask, parent: Option<&str>) -> Result<()> {
+    let mut tags: Vec<&str> = task.tags.iter().map(|t| t.as_str()).collect();
+    tags.sort();
+    // A parent's duration is left out when it's just the sum of its subtasks
+    let derived = task.subtasks.iter().map(|t| t.duration_minutes()).sum::<u64>();
+    let duration = task.duration.as_ref()
+        .filter(|d| task.subtasks.is_empty() || d.minutes != derived)
+        .map(|d| d.minutes.to_string());
+    let time = |time: Option<DateTime<Local>>| time.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+
+    writer.write_record([
+        task.id.clone(),
+        task.block.clone(),
+        task.name.clone(),
+        task.params.join(","),
+        duration.unwrap_or_default(),
+        format!("{:?}", task.priority).to_lowercase(),
+        tags.join(","),
+        task.depends_on.join(","),
+        time(task.scheduled_start),
+        time(task.scheduled_end),
+        task.completed.to_string(),
+        parent.unwrap_or_default().to_string(),
+        task.link.clone().unwrap_or_default(),
+        task.notes.join("\n\n"),
+    ])?;
+
+    for subtask in &task.subtasks {
+        write_task(writer, subtask, Some(&task.id))?;
+    }
+    Ok(())
+}
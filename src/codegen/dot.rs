@@ -0,0 +1,168 @@
+//! The dependency graph in Graphviz DOT, to pipe into `dot`.
+//!
+//! Each block is a cluster and each task a node, coloured by priority and
+//! labelled with its duration. Solid edges run from a dependency to the task
+//! waiting on it, dashed ones from a parent to its subtasks. The critical
+//! path, the longest chain of dependencies by duration, is drawn in bold.
+
+use crate::ast::Priority;
+use crate::ir::{IRProgram, IRTask};
+use crate::utils::format_duration;
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+pub fn emit_dot(program: &IRProgram) -> Result<String> {
+    let tasks: Vec<&IRTask> = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .collect();
+    let by_id: HashMap<&str, &IRTask> = tasks.iter().map(|t| (t.id.as_str(), *t)).collect();
+    let (path, total) = critical_path(&tasks, &by_id);
+    let on_path: HashSet<&str> = path.iter().copied().collect();
+    let path_edges: HashSet<(&str, &str)> = path.windows(2).map(|pair| (pair[0], pair[1])).collect();
+
+    let mut output = String::new();
+    writeln!(&mut output, "digraph zibox {{")?;
+    writeln!(&mut output, "  rankdir=LR;")?;
+    if path.len() > 1 {
+        writeln!(&mut output, "  label=\"Critical path (bold): {}\";", format_duration(total))?;
+    }
+    writeln!(&mut output, "  fontname=\"sans-serif\";")?;
+    writeln!(&mut output, "  node [shape=box, style=\"rounded,filled\", fontname=\"sans-serif\", fontcolor=white];")?;
+    writeln!(&mut output, "  edge [color=\"#757575\"];")?;
+
+    for (index, block) in program.blocks.iter().enumerate() {
+        writeln!(&mut output)?;
+        writeln!(&mut output, "  subgraph cluster_{} {{", index)?;
+        writeln!(&mut output, "    label=\"{}\";", escape(&block.name))?;
+        writeln!(&mut output, "    style=\"rounded\";")?;
+        writeln!(&mut output, "    color=\"#bdbdbd\";")?;
+        for task in block.tasks.iter().flat_map(|t| t.flatten()) {
+            // Done tasks are greyed out, whatever their priority
+            let fill = if task.completed { "#bdbdbd" } else { priority_colour(&task.priority) };
+            let mut attributes = vec![format!("label=\"{}\"", label(task)), format!("fillcolor=\"{}\"", fill)];
+            if on_path.contains(task.id.as_str()) && path.len() > 1 {
+                attributes.push("color=black".to_string());
+                attributes.push("penwidth=3".to_string());
+            }
+            writeln!(&mut output, "    \"{}\" [{}];", escape(&task.id), attributes.join(", "))?;
+        }
+        writeln!(&mut output, "  }}")?;
+    }
+
+    writeln!(&mut output)?;
+    for task in &tasks {
+        for subtask in &task.subtasks {
+            writeln!(
+                &mut output,
+                "  \"{}\" -> \"{}\" [style=dashed, arrowhead=none];",
+                escape(&task.id),
+                escape(&subtask.id)
+            )?;
+        }
+        for dependency in task.depends_on.iter().filter(|d| by_id.contains_key(d.as_str())) {
+            let bold = if path_edges.contains(&(dependency.as_str(), task.id.as_str())) {
+                " [color=black, penwidth=3]"
+            } else {
+                ""
+            };
+            writeln!(&mut output, "  \"{}\" -> \"{}\"{};", escape(dependency), escape(&task.id), bold)?;
+        }
+    }
+    writeln!(&mut output, "}}")?;
+
+    Ok(output)
+}
+
+/// The longest chain of dependencies by duration, first task first, and its
+/// total length. Ties go to the task that comes first in the plan.
+fn critical_path<'a>(tasks: &[&'a IRTask], by_id: &HashMap<&str, &'a IRTask>) -> (Vec<&'a str>, u64) {
+    fn finish<'a>(
+        task: &'a IRTask,
+        by_id: &HashMap<&str, &'a IRTask>,
+        finishes: &mut HashMap<&'a str, u64>,
+        visiting: &mut HashSet<&'a str>,
+    ) -> u64 {
+        if let Some(&done) = finishes.get(task.id.as_str()) {
+            return done;
+        }
+        // A cycle adds nothing; the optimizer reports those
+        if !visiting.insert(task.id.as_str()) {
+            return 0;
+        }
+        let before = task.depends_on.iter()
+            .filter_map(|d| by_id.get(d.as_str()))
+            .map(|d| finish(d, by_id, finishes, visiting))
+            .max()
+            .unwrap_or(0);
+        visiting.remove(task.id.as_str());
+        let total = before + length(task);
+        finishes.insert(task.id.as_str(), total);
+        total
+    }
+
+    let mut finishes = HashMap::new();
+    let mut visiting = HashSet::new();
+    let mut last: Option<(&IRTask, u64)> = None;
+    for task in tasks {
+        let total = finish(task, by_id, &mut finishes, &mut visiting);
+        if last.is_none_or(|(_, best)| total > best) {
+            last = Some((task, total));
+        }
+    }
+    let Some((mut task, total)) = last.filter(|&(_, total)| total > 0) else {
+        return (Vec::new(), 0);
+    };
+
+    // Walk back through whichever dependency finishes last
+    let mut path = vec![task.id.as_str()];
+    let mut seen: HashSet<&str> = path.iter().copied().collect();
+    loop {
+        let previous = task.depends_on.iter()
+            .filter_map(|d| by_id.get(d.as_str()).copied())
+            .filter(|d| !seen.contains(d.id.as_str()))
+            .fold(None, |best: Option<&IRTask>, d| match best {
+                Some(b) if finishes[b.id.as_str()] >= finishes[d.id.as_str()] => Some(b),
+                _ => Some(d),
+            });
+        let Some(previous) = previous else {
+            break;
+        };
+        path.push(previous.id.as_str());
+        seen.insert(previous.id.as_str());
+        task = previous;
+    }
+    path.reverse();
+    (path, total)
+}
+
+/// A task's own duration, or for a parent without one, its subtasks'
+fn length(task: &IRTask) -> u64 {
+    match task.duration_minutes() {
+        0 => task.subtasks.iter().map(length).sum(),
+        minutes => minutes,
+    }
+}
+
+fn label(task: &IRTask) -> String {
+    match length(task) {
+        0 => escape(&task.display_name()),
+        minutes => format!("{}\\n{}", escape(&task.display_name()), format_duration(minutes)),
+    }
+}
+
+/// The same colours as the HTML planner
+fn priority_colour(priority: &Priority) -> &'static str {
+    match priority {
+        Priority::Critical => "#c62828",
+        Priority::High => "#ef6c00",
+        Priority::Medium => "#1565c0",
+        Priority::Low => "#78909c",
+    }
+}
+
+/// Quotes and backslashes are the only specials in a quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,198 @@
+//! Gantt diagrams as text, for Mermaid and PlantUML.
+//!
+//! Both have a section per block and a row per task, subtasks included.
+//! Mermaid gets the schedule as is: a task that starts right when its last
+//! dependency ends is placed `after` it, so the renderer draws the link, and
+//! any other scheduled task gets its own start time. PlantUML has no time of
+//! day, so there tasks are linked in plan order instead. Critical tasks are
+//! highlighted and completed ones marked done.
+
+use crate::ast::Priority;
+use crate::ir::{IRProgram, IRTask};
+use crate::utils::format_duration;
+use anyhow::{Result, anyhow};
+use std::fmt::Write;
+
+pub fn emit_mermaid(program: &IRProgram) -> Result<String> {
+    let mut output = String::new();
+    writeln!(&mut output, "gantt")?;
+    writeln!(&mut output, "    title Zero Inbox Plan")?;
+    writeln!(&mut output, "    dateFormat YYYY-MM-DD HH:mm")?;
+    writeln!(&mut output, "    axisFormat %H:%M")?;
+
+    for block in &program.blocks {
+        writeln!(&mut output, "    section {}", mermaid_text(&block.name))?;
+        for task in block.tasks.iter().flat_map(|t| t.flatten()) {
+            let mut fields = Vec::new();
+            if task.priority == Priority::Critical {
+                fields.push("crit".to_string());
+            }
+            if task.completed {
+                fields.push("done".to_string());
+            }
+            let minutes = task.duration_minutes();
+            if minutes == 0 {
+                fields.push("milestone".to_string());
+            }
+            fields.push(diagram_id(&task.id));
+
+            match (after(program, task), task.scheduled_start) {
+                (Some(dependencies), _) => {
+                    let ids: Vec<String> = dependencies.iter().map(|d| diagram_id(&d.id)).collect();
+                    fields.push(format!("after {}", ids.join(" ")));
+                },
+                (None, Some(start)) => fields.push(start.format("%Y-%m-%d %H:%M").to_string()),
+                // Unscheduled, so it just follows the task before it
+                (None, None) => {},
+            }
+            fields.push(format!("{}m", minutes));
+
+            writeln!(&mut output, "    {} :{}", mermaid_text(&task.display_name()), fields.join(", "))?;
+        }
+    }
+
+    Ok(output)
+}
+
+/// PlantUML counts in whole days and has no clock, so the diagram keeps
+/// each task's length and order but not its time of day. A column stands
+/// for the largest step every duration is a multiple of. Each task starts
+/// when its last dependency ends, or else right after the task before it
+/// (a first subtask with its parent), so gaps in the schedule are left out.
+pub fn emit_plantuml(program: &IRProgram) -> Result<String> {
+    let slot = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .map(|t| t.duration_minutes())
+        .filter(|&minutes| minutes > 0)
+        .fold(0, gcd);
+    if slot == 0 {
+        return Err(anyhow!("Nothing to chart: no task has a duration"));
+    }
+
+    let mut layout = Layout { program, slot, waiting: Vec::new(), output: String::new() };
+    writeln!(&mut layout.output, "@startgantt")?;
+    writeln!(&mut layout.output, "title Zero Inbox Plan")?;
+    writeln!(
+        &mut layout.output,
+        "caption One column is {}; tasks are drawn in plan order, without clock times",
+        format_duration(slot)
+    )?;
+
+    let mut anchor = None;
+    for block in &program.blocks {
+        writeln!(&mut layout.output, "-- {} --", plantuml_text(&block.name))?;
+        for task in &block.tasks {
+            anchor = layout.task(task, anchor)?;
+        }
+    }
+    writeln!(&mut layout.output, "@endgantt")?;
+
+    Ok(layout.output)
+}
+
+/// PlantUML output under way
+struct Layout<'a> {
+    program: &'a IRProgram,
+    /// Minutes per column
+    slot: u64,
+    /// Milestones at the very start, which wait for a bar to be placed on
+    waiting: Vec<String>,
+    output: String,
+}
+
+impl Layout<'_> {
+    /// Write a task and its subtasks, starting at `anchor` (like `[id]'s
+    /// end`) unless a dependency says otherwise, and return where the next
+    /// task starts
+    fn task(&mut self, task: &IRTask, anchor: Option<String>) -> Result<Option<String>> {
+        let name = plantuml_text(&task.display_name());
+        // PlantUML links to one task; the last to finish decides the start
+        let dependency = task.depends_on.iter()
+            .filter_map(|id| self.program.get_task(id))
+            .max_by_key(|d| d.scheduled_end)
+            .map(|d| format!("[{}]'s end", diagram_id(&d.id)));
+        let start = dependency.or(anchor);
+
+        let minutes = task.duration_minutes();
+        if minutes == 0 {
+            match &start {
+                Some(start) => writeln!(&mut self.output, "[{}] happens at {}", name, start)?,
+                None => self.waiting.push(name),
+            }
+            // Its subtasks are milestones too, at the same moment
+            for subtask in &task.subtasks {
+                self.task(subtask, start.clone())?;
+            }
+            return Ok(start);
+        }
+
+        let id = diagram_id(&task.id);
+        writeln!(&mut self.output, "[{}] as [{}] lasts {} days", name, id, minutes / self.slot)?;
+        match &start {
+            Some(start) => writeln!(&mut self.output, "[{}] starts at {}", id, start)?,
+            None => {
+                for milestone in std::mem::take(&mut self.waiting) {
+                    writeln!(&mut self.output, "[{}] happens at [{}]'s start", milestone, id)?;
+                }
+            },
+        }
+        if task.priority == Priority::Critical {
+            writeln!(&mut self.output, "[{}] is colored in Red", id)?;
+        }
+        if task.completed {
+            writeln!(&mut self.output, "[{}] is 100% completed", id)?;
+        }
+
+        let mut anchor = Some(format!("[{}]'s start", id));
+        for subtask in &task.subtasks {
+            anchor = self.task(subtask, anchor)?;
+        }
+        Ok(Some(format!("[{}]'s end", id)))
+    }
+}
+
+/// The dependencies to place a task after: all of them, if they're in the
+/// plan and the task starts the moment the last one ends (or isn't
+/// scheduled at all)
+fn after<'a>(program: &'a IRProgram, task: &IRTask) -> Option<Vec<&'a IRTask>> {
+    if task.depends_on.is_empty() {
+        return None;
+    }
+    let dependencies: Vec<&IRTask> = task.depends_on.iter()
+        .map(|id| program.get_task(id))
+        .collect::<Option<_>>()?;
+    match task.scheduled_start {
+        None => Some(dependencies),
+        Some(start) => {
+            let last_end = dependencies.iter().map(|d| d.scheduled_end).max()??;
+            (last_end == start).then_some(dependencies)
+        },
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Both take IDs of letters, digits and underscores
+fn diagram_id(id: &str) -> String {
+    id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect()
+}
+
+/// `:`, `;` and `#` are syntax in a task line, so they're written as entities
+fn mermaid_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '#' | ':' | ';' => escaped.push_str(&format!("#{};", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Square brackets delimit task names
+fn plantuml_text(text: &str) -> String {
+    text.replace('[', "(").replace(']', ")")
+}
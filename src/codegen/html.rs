@@ -0,0 +1,264 @@
+//! A day planner as a single HTML file.
+//!
+//! Styles and script are inline, so the file works offline and can be mailed
+//! around. A timeline shows each scheduled task as a bar; below it, each
+//! block lists its tasks with priority and tag colours, what they wait for,
+//! and a checkbox whose state the browser remembers in `localStorage`, per
+//! plan date, so tomorrow's plan doesn't start with today's ticks. Links are
+//! only clickable when they're web or mail addresses.
+
+use crate::ast::Priority;
+use crate::ir::{IRProgram, IRTask};
+use crate::utils::{format_duration, stable_hash};
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Timelike};
+use std::fmt::Write;
+
+const STYLE: &str = r#"
+  :root { --critical: #c62828; --high: #ef6c00; --medium: #1565c0; --low: #78909c; }
+  body { font: 15px/1.5 system-ui, sans-serif; max-width: 960px; margin: 2em auto; padding: 0 1em; color: #222; }
+  h1 { margin-bottom: 0; }
+  .generated { color: #777; margin-top: 0; }
+  .timeline { position: relative; border-left: 1px solid #ccc; margin: 1.5em 0 2em; }
+  .hours { position: relative; height: 1.4em; font-size: 12px; color: #777; }
+  .hours span { position: absolute; transform: translateX(-50%); }
+  .lane { position: relative; height: 1.8em; margin: 2px 0; }
+  .bar { position: absolute; height: 100%; border-radius: 4px; color: #fff; font-size: 12px; line-height: 1.8em;
+         padding: 0 6px; box-sizing: border-box; overflow: hidden; white-space: nowrap; text-decoration: none; }
+  .bar.done { opacity: 0.45; }
+  section { margin-bottom: 1.5em; }
+  ul.tasks { list-style: none; padding-left: 0; }
+  ul.tasks ul.tasks { padding-left: 1.6em; }
+  li.task { margin: 0.4em 0; padding-left: 0.6em; border-left: 4px solid var(--medium); }
+  li.task.p-critical { border-color: var(--critical); }
+  li.task.p-high { border-color: var(--high); }
+  li.task.p-low { border-color: var(--low); }
+  li.task.done > .line .name { text-decoration: line-through; color: #888; }
+  .bar.p-critical { background: var(--critical); }
+  .bar.p-high { background: var(--high); }
+  .bar.p-medium { background: var(--medium); }
+  .bar.p-low { background: var(--low); }
+  .time { font-variant-numeric: tabular-nums; color: #555; margin-right: 0.4em; }
+  .name { font-weight: 600; }
+  .duration { color: #777; margin-left: 0.4em; }
+  .priority { font-size: 12px; text-transform: uppercase; margin-left: 0.4em; }
+  .priority.p-critical { color: var(--critical); }
+  .priority.p-high { color: var(--high); }
+  .tag { display: inline-block; font-size: 12px; border-radius: 8px; padding: 0 7px; margin-left: 0.3em; color: #fff; }
+  .deps, .notes, .link { font-size: 13px; color: #555; margin-left: 1.7em; }
+  .notes p { margin: 0.2em 0; white-space: pre-wrap; }
+"#;
+
+const SCRIPT: &str = r#"
+  function mark(box) {
+    box.closest('li').classList.toggle('done', box.checked);
+    document.querySelectorAll('.bar').forEach(function (bar) {
+      if (bar.dataset.task === box.dataset.task) bar.classList.toggle('done', box.checked);
+    });
+  }
+  document.querySelectorAll('input[data-task]').forEach(function (box) {
+    var key = 'zibox:' + document.body.dataset.plan + ':' + box.dataset.task;
+    try {
+      var saved = localStorage.getItem(key);
+      if (saved !== null) box.checked = saved === '1';
+    } catch (e) {}
+    mark(box);
+    box.addEventListener('change', function () {
+      try { localStorage.setItem(key, box.checked ? '1' : '0'); } catch (e) {}
+      mark(box);
+    });
+  });
+"#;
+
+pub fn emit_html(program: &IRProgram) -> Result<String> {
+    let mut output = String::new();
+    writeln!(&mut output, "<!DOCTYPE html>")?;
+    writeln!(&mut output, "<html lang=\"en\">")?;
+    writeln!(&mut output, "<head>")?;
+    writeln!(&mut output, "<meta charset=\"utf-8\">")?;
+    writeln!(&mut output, "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">")?;
+    writeln!(&mut output, "<title>Zero Inbox Plan</title>")?;
+    writeln!(&mut output, "<style>{}</style>", STYLE)?;
+    writeln!(&mut output, "</head>")?;
+    writeln!(&mut output, "<body data-plan=\"{}\">", plan_date(program).format("%Y-%m-%d"))?;
+    writeln!(&mut output, "<h1>Zero Inbox Plan</h1>")?;
+    writeln!(
+        &mut output,
+        "<p class=\"generated\">Generated on {}</p>",
+        Local::now().format("%Y-%m-%d %H:%M")
+    )?;
+
+    write_timeline(&mut output, program)?;
+
+    for block in &program.blocks {
+        writeln!(&mut output, "<section>")?;
+        writeln!(&mut output, "<h2>{}</h2>", escape(&block.name))?;
+        writeln!(&mut output, "<ul class=\"tasks\">")?;
+        for task in &block.tasks {
+            write_task(&mut output, program, task)?;
+        }
+        writeln!(&mut output, "</ul>")?;
+        writeln!(&mut output, "</section>")?;
+    }
+
+    writeln!(&mut output, "<script>{}</script>", SCRIPT)?;
+    writeln!(&mut output, "</body>")?;
+    writeln!(&mut output, "</html>")?;
+    Ok(output)
+}
+
+/// One lane per scheduled top-level task, scaled to the hours they cover
+fn write_timeline(output: &mut String, program: &IRProgram) -> Result<()> {
+    let scheduled: Vec<(&IRTask, DateTime<Local>, DateTime<Local>)> = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .filter_map(|t| Some((t, t.scheduled_start?, t.scheduled_end?)))
+        .collect();
+    let (Some(first), Some(last)) = (
+        scheduled.iter().map(|&(_, start, _)| start).min(),
+        scheduled.iter().map(|&(_, _, end)| end).max(),
+    ) else {
+        return Ok(());
+    };
+
+    // Whole hours either side, so the scale has round numbers
+    let from = first.with_minute(0).and_then(|t| t.with_second(0)).unwrap_or(first);
+    let hours = ((last - from).num_minutes() as f64 / 60.0).ceil().max(1.0) as i64;
+    let span = (hours * 60) as f64;
+    let percent = |time: DateTime<Local>| (time - from).num_minutes() as f64 / span * 100.0;
+
+    writeln!(output, "<div class=\"timeline\">")?;
+    write!(output, "<div class=\"hours\">")?;
+    for hour in 0..=hours {
+        let time = from + chrono::Duration::hours(hour);
+        write!(output, "<span style=\"left:{:.2}%\">{}</span>", percent(time), time.format("%H:%M"))?;
+    }
+    writeln!(output, "</div>")?;
+    for (task, start, end) in scheduled {
+        writeln!(
+            output,
+            "<div class=\"lane\"><a class=\"bar p-{}{}\" href=\"#{}\" data-task=\"{}\" style=\"left:{:.2}%;width:{:.2}%\" title=\"{}–{} {}\">{}</a></div>",
+            priority_class(&task.priority),
+            if task.completed { " done" } else { "" },
+            escape(&task.id),
+            escape(&task.id),
+            percent(start),
+            percent(end) - percent(start),
+            start.format("%H:%M"),
+            end.format("%H:%M"),
+            escape(&task.display_name()),
+            escape(&task.display_name()),
+        )?;
+    }
+    writeln!(output, "</div>")?;
+    Ok(())
+}
+
+fn write_task(output: &mut String, program: &IRProgram, task: &IRTask) -> Result<()> {
+    let priority = priority_class(&task.priority);
+    writeln!(output, "<li class=\"task p-{}\" id=\"{}\">", priority, escape(&task.id))?;
+
+    write!(output, "<label class=\"line\">")?;
+    write!(
+        output,
+        "<input type=\"checkbox\" data-task=\"{}\"{}> ",
+        escape(&task.id),
+        if task.completed { " checked" } else { "" }
+    )?;
+    if let (Some(start), Some(end)) = (task.scheduled_start, task.scheduled_end) {
+        write!(output, "<span class=\"time\">{}–{}</span>", start.format("%H:%M"), end.format("%H:%M"))?;
+    }
+    write!(output, "<span class=\"name\">{}</span>", escape(&task.display_name()))?;
+    let duration = task.duration_minutes();
+    if duration > 0 {
+        write!(output, "<span class=\"duration\">{}</span>", format_duration(duration))?;
+    }
+    if task.priority != Priority::Medium {
+        write!(output, "<span class=\"priority p-{}\">{}</span>", priority, priority)?;
+    }
+    let mut tags: Vec<&String> = task.tags.iter().collect();
+    tags.sort();
+    for tag in tags {
+        write!(output, "<span class=\"tag\" style=\"background:{}\">#{}</span>", tag_colour(tag), escape(tag))?;
+    }
+    writeln!(output, "</label>")?;
+
+    if !task.depends_on.is_empty() {
+        let deps: Vec<String> = task.depends_on.iter()
+            .map(|dep| match program.get_task(dep) {
+                Some(target) => format!("<a href=\"#{}\">{}</a>", escape(&target.id), escape(&target.display_name())),
+                None => escape(dep),
+            })
+            .collect();
+        writeln!(output, "<div class=\"deps\">After: {}</div>", deps.join(", "))?;
+    }
+    if !task.notes.is_empty() {
+        write!(output, "<div class=\"notes\">")?;
+        for note in &task.notes {
+            write!(output, "<p>{}</p>", escape(note))?;
+        }
+        writeln!(output, "</div>")?;
+    }
+    if let Some(link) = &task.link {
+        if is_web_link(link) {
+            writeln!(output, "<div class=\"link\"><a href=\"{}\">{}</a></div>", escape(link), escape(link))?;
+        } else {
+            writeln!(output, "<div class=\"link\">{}</div>", escape(link))?;
+        }
+    }
+
+    if !task.subtasks.is_empty() {
+        writeln!(output, "<ul class=\"tasks\">")?;
+        for subtask in &task.subtasks {
+            write_task(output, program, subtask)?;
+        }
+        writeln!(output, "</ul>")?;
+    }
+    writeln!(output, "</li>")?;
+    Ok(())
+}
+
+/// The day the plan is for: when its first task starts, or today
+fn plan_date(program: &IRProgram) -> NaiveDate {
+    program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .filter_map(|t| t.scheduled_start)
+        .min()
+        .unwrap_or_else(Local::now)
+        .date_naive()
+}
+
+/// Whether a link is safe to make clickable; anything else, `javascript:`
+/// included, is shown as text
+fn is_web_link(link: &str) -> bool {
+    let link = link.to_ascii_lowercase();
+    ["http://", "https://", "mailto:"].iter().any(|scheme| link.starts_with(scheme))
+}
+
+fn priority_class(priority: &Priority) -> &'static str {
+    match priority {
+        Priority::Critical => "critical",
+        Priority::High => "high",
+        Priority::Medium => "medium",
+        Priority::Low => "low",
+    }
+}
+
+/// A colour per tag, the same in every plan
+fn tag_colour(tag: &str) -> String {
+    format!("hsl({}, 55%, 42%)", stable_hash(tag) % 360)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
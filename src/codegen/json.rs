@@ -0,0 +1,286 @@
+//! The JSON output model.
+//!
+//! Deliberately separate from the IR structs so that refactoring the compiler
+//! doesn't change what other tools read. Keys are written in alphabetical
+//! order, sets (tags, focus tags) are sorted, and each task appears exactly
+//! once, nested in its block. [`json_schema`] describes the same layout;
+//! bump [`JSON_SCHEMA_VERSION`] whenever a change would break an old reader.
+
+use crate::ast::{Priority, TaskDuration};
+use crate::ir::{IRBlock, IRMetadata, IRProgram, IRTask};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::str::FromStr;
+
+pub const JSON_SCHEMA_VERSION: u32 = 3;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonPlan {
+    pub blocks: Vec<JsonBlock>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<JsonMetadata>,
+    pub schema_version: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonBlock {
+    pub name: String,
+    #[serde(default)]
+    pub tasks: Vec<JsonTask>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonTask {
+    #[serde(default)]
+    pub completed: bool,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u64>,
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub notes: Vec<String>,
+    #[serde(default)]
+    pub params: Vec<String>,
+    #[serde(default = "default_priority")]
+    pub priority: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_end: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_start: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub subtasks: Vec<JsonTask>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct JsonMetadata {
+    pub focus_tags: Vec<String>,
+    pub max_parallel: usize,
+    pub optimization_level: u8,
+    pub timezone: String,
+    /// `HH:MM`
+    pub workday_end: String,
+    /// `HH:MM`
+    pub workday_start: String,
+}
+
+impl Default for JsonMetadata {
+    fn default() -> Self {
+        Self::from(&IRMetadata::default())
+    }
+}
+
+fn default_priority() -> String {
+    "medium".to_string()
+}
+
+impl From<&IRProgram> for JsonPlan {
+    fn from(program: &IRProgram) -> Self {
+        Self {
+            blocks: program.blocks.iter()
+                .map(|block| JsonBlock {
+                    name: block.name.clone(),
+                    tasks: block.tasks.iter().map(JsonTask::from).collect(),
+                })
+                .collect(),
+            metadata: Some(JsonMetadata::from(&program.metadata)),
+            schema_version: JSON_SCHEMA_VERSION,
+        }
+    }
+}
+
+impl From<&IRTask> for JsonTask {
+    fn from(task: &IRTask) -> Self {
+        let mut tags: Vec<String> = task.tags.iter().cloned().collect();
+        tags.sort();
+        Self {
+            completed: task.completed,
+            depends_on: task.depends_on.clone(),
+            due: task.due,
+            duration_minutes: task.duration.as_ref().map(|d| d.minutes),
+            id: task.id.clone(),
+            link: task.link.clone(),
+            name: task.name.clone(),
+            notes: task.notes.clone(),
+            params: task.params.clone(),
+            priority: format!("{:?}", task.priority).to_lowercase(),
+            run: task.run.clone(),
+            scheduled_end: task.scheduled_end,
+            scheduled_start: task.scheduled_start,
+            subtasks: task.subtasks.iter().map(JsonTask::from).collect(),
+            tags,
+        }
+    }
+}
+
+impl From<&IRMetadata> for JsonMetadata {
+    fn from(metadata: &IRMetadata) -> Self {
+        let mut focus_tags = metadata.focus_tags.clone();
+        focus_tags.sort();
+        Self {
+            focus_tags,
+            max_parallel: metadata.max_parallel,
+            optimization_level: metadata.optimization_level,
+            timezone: metadata.timezone.clone(),
+            workday_end: metadata.workday_end.format("%H:%M").to_string(),
+            workday_start: metadata.workday_start.format("%H:%M").to_string(),
+        }
+    }
+}
+
+impl JsonBlock {
+    pub fn to_ir(&self) -> Result<IRBlock> {
+        Ok(IRBlock {
+            name: self.name.clone(),
+            tasks: self.tasks.iter()
+                .map(|task| task.to_ir(&self.name))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl JsonTask {
+    pub fn to_ir(&self, block: &str) -> Result<IRTask> {
+        let priority = Priority::from_str(&self.priority)
+            .map_err(|_| anyhow!("Task '{}' has invalid priority '{}'", self.id, self.priority))?;
+        Ok(IRTask {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            params: self.params.clone(),
+            duration: self.duration_minutes.map(TaskDuration::from_minutes),
+            block: block.to_string(),
+            tags: self.tags.iter().cloned().collect(),
+            priority,
+            depends_on: self.depends_on.clone(),
+            scheduled_start: self.scheduled_start,
+            scheduled_end: self.scheduled_end,
+            completed: self.completed,
+            subtasks: self.subtasks.iter()
+                .map(|subtask| subtask.to_ir(block))
+                .collect::<Result<_>>()?,
+            notes: self.notes.clone(),
+            link: self.link.clone(),
+            due: self.due,
+            run: self.run.clone(),
+        })
+    }
+}
+
+impl JsonMetadata {
+    pub fn to_ir(&self) -> Result<IRMetadata> {
+        let time = |s: &str| NaiveTime::parse_from_str(s, "%H:%M")
+            .map_err(|_| anyhow!("Invalid time '{}' (expected HH:MM)", s));
+        Ok(IRMetadata {
+            timezone: self.timezone.clone(),
+            workday_start: time(&self.workday_start)?,
+            workday_end: time(&self.workday_end)?,
+            max_parallel: self.max_parallel,
+            focus_tags: self.focus_tags.clone(),
+            optimization_level: self.optimization_level,
+        })
+    }
+}
+
+pub fn emit_json(program: &IRProgram) -> Result<String> {
+    serde_json::to_string_pretty(&JsonPlan::from(program))
+        .map_err(|e| anyhow!("JSON serialization error: {}", e))
+}
+
+/// JSON Schema (draft 2020-12) for the current output model
+pub fn json_schema() -> serde_json::Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Zero Inbox plan",
+        "type": "object",
+        "required": ["blocks", "schema_version"],
+        "additionalProperties": false,
+        "properties": {
+            "blocks": {
+                "type": "array",
+                "items": { "$ref": "#/$defs/block" }
+            },
+            "metadata": { "$ref": "#/$defs/metadata" },
+            "schema_version": { "const": JSON_SCHEMA_VERSION }
+        },
+        "$defs": {
+            "block": {
+                "type": "object",
+                "required": ["name"],
+                "additionalProperties": false,
+                "properties": {
+                    "name": { "type": "string" },
+                    "tasks": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/task" }
+                    }
+                }
+            },
+            "task": {
+                "type": "object",
+                "required": ["id", "name"],
+                "additionalProperties": false,
+                "properties": {
+                    "completed": { "type": "boolean", "default": false },
+                    "depends_on": {
+                        "description": "IDs of the tasks this one waits for; names are resolved like `after:`",
+                        "type": "array",
+                        "items": { "type": "string" }
+                    },
+                    "due": { "type": "string", "format": "date" },
+                    "duration_minutes": { "type": "integer", "minimum": 0 },
+                    "id": { "type": "string", "minLength": 1 },
+                    "link": { "type": "string" },
+                    "name": { "type": "string" },
+                    "notes": { "type": "array", "items": { "type": "string" } },
+                    "params": { "type": "array", "items": { "type": "string" } },
+                    "priority": {
+                        "enum": ["low", "medium", "high", "critical"],
+                        "default": "medium"
+                    },
+                    "run": {
+                        "description": "Command to launch when the task starts, like `run:`; only allow-listed programs run",
+                        "type": "string"
+                    },
+                    "scheduled_end": { "type": "string", "format": "date-time" },
+                    "scheduled_start": { "type": "string", "format": "date-time" },
+                    "subtasks": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/task" }
+                    },
+                    "tags": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "uniqueItems": true
+                    }
+                }
+            },
+            "metadata": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "focus_tags": { "type": "array", "items": { "type": "string" } },
+                    "max_parallel": { "type": "integer", "minimum": 0 },
+                    "optimization_level": { "type": "integer", "minimum": 0, "maximum": 255 },
+                    "timezone": { "type": "string" },
+                    "workday_end": { "type": "string", "pattern": "^[0-9]{2}:[0-9]{2}$" },
+                    "workday_start": { "type": "string", "pattern": "^[0-9]{2}:[0-9]{2}$" }
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,114 @@
+//! Org-mode, for Emacs.
+//!
+//! Blocks are top-level headings and tasks `TODO` or `DONE` entries below
+//! them, subtasks nested one level further. Priorities use four cookies,
+//! `[#A]` (critical) to `[#D]` (low), declared in the file header. A scheduled
+//! task gets a `SCHEDULED:` timestamp covering its slot, and a task with a
+//! duration an `:EFFORT:` property. Notes follow as plain paragraphs.
+
+use crate::ast::Priority;
+use crate::ir::{IRProgram, IRTask};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use std::fmt::Write;
+
+/// Org's default `org-tags-column`
+const TAGS_COLUMN: usize = 77;
+
+pub fn emit_org(program: &IRProgram) -> Result<String> {
+    let mut output = String::new();
+    writeln!(&mut output, "#+TITLE: Zero Inbox Plan")?;
+    writeln!(&mut output, "#+DATE: {}", Local::now().format("[%Y-%m-%d %a %H:%M]"))?;
+    // Highest, lowest and default priority
+    writeln!(&mut output, "#+PRIORITIES: A D C")?;
+
+    for block in &program.blocks {
+        writeln!(&mut output)?;
+        writeln!(&mut output, "* {}", block.name)?;
+        for task in &block.tasks {
+            write_task(&mut output, task, 2)?;
+        }
+    }
+
+    Ok(output)
+}
+
+fn write_task(output: &mut String, task: &IRTask, level: usize) -> Result<()> {
+    let keyword = if task.completed { "DONE" } else { "TODO" };
+    let headline = format!(
+        "{} {} [#{}] {}",
+        "*".repeat(level),
+        keyword,
+        priority_cookie(&task.priority),
+        task.display_name()
+    );
+    writeln!(output, "{}", with_tags(headline, task))?;
+
+    if let (Some(start), Some(end)) = (task.scheduled_start, task.scheduled_end) {
+        writeln!(output, "SCHEDULED: {}", timestamp(start, end))?;
+    }
+    // A parent's duration is left out when it's just the sum of its subtasks,
+    // since Org adds those up itself
+    let derived = task.subtasks.iter().map(|t| t.duration_minutes()).sum::<u64>();
+    if let Some(duration) = &task.duration {
+        if task.subtasks.is_empty() || duration.minutes != derived {
+            writeln!(output, ":PROPERTIES:")?;
+            writeln!(output, ":EFFORT:   {}:{:02}", duration.minutes / 60, duration.minutes % 60)?;
+            writeln!(output, ":END:")?;
+        }
+    }
+
+    for note in &task.notes {
+        for line in note.lines() {
+            // A leading `*` would start a heading
+            let line = if line.starts_with('*') { format!(",{}", line) } else { line.to_string() };
+            writeln!(output, "{}", line)?;
+        }
+    }
+    if let Some(link) = &task.link {
+        writeln!(output, "[[{}]]", link)?;
+    }
+
+    for subtask in &task.subtasks {
+        write_task(output, subtask, level + 1)?;
+    }
+    Ok(())
+}
+
+fn priority_cookie(priority: &Priority) -> char {
+    match priority {
+        Priority::Critical => 'A',
+        Priority::High => 'B',
+        Priority::Medium => 'C',
+        Priority::Low => 'D',
+    }
+}
+
+/// The headline with its tags right-aligned at [`TAGS_COLUMN`], as Org
+/// aligns them
+fn with_tags(headline: String, task: &IRTask) -> String {
+    if task.tags.is_empty() {
+        return headline;
+    }
+    // Org tags are letters, digits, `_`, `@`, `#` and `%`
+    let mut tags: Vec<String> = task.tags.iter()
+        .map(|tag| tag.chars()
+            .map(|c| if c.is_alphanumeric() || "_@#%".contains(c) { c } else { '_' })
+            .collect())
+        .collect();
+    tags.sort();
+    let tags = format!(":{}:", tags.join(":"));
+
+    let width = headline.chars().count() + tags.chars().count();
+    let padding = TAGS_COLUMN.saturating_sub(width).max(1);
+    format!("{}{}{}", headline, " ".repeat(padding), tags)
+}
+
+/// `<2026-10-16 Fri 09:00-10:00>`, or a range when the slot crosses midnight
+fn timestamp(start: DateTime<Local>, end: DateTime<Local>) -> String {
+    if start.date_naive() == end.date_naive() {
+        format!("<{}-{}>", start.format("%Y-%m-%d %a %H:%M"), end.format("%H:%M"))
+    } else {
+        format!("<{}>--<{}>", start.format("%Y-%m-%d %a %H:%M"), end.format("%Y-%m-%d %a %H:%M"))
+    }
+}
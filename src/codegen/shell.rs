@@ -0,0 +1,340 @@
+//! Scripts that walk through the plan: bash, strict POSIX sh and PowerShell.
+//!
+//! All three share one walk; a `Dialect` says how each prints, notifies,
+//! launches a command and waits. Task names, parameters and block names come
+//! from whoever wrote the plan, so every one of them goes through the
+//! dialect's `quote` before it reaches the script: a name like `$(rm -rf ~)`
+//! is printed, never run. A task's command (see `actions`) is launched in the
+//! background as it starts, unless the script runs with `ZIBOX_DRY_RUN=1`,
+//! which prints it instead.
+
+use crate::actions::Actions;
+use crate::ir::IRProgram;
+use crate::utils::format_duration;
+use anyhow::Result;
+use chrono::Local;
+use std::fmt::Write;
+
+const BASH_HEADER: &str = r#"set -euo pipefail
+
+# Check if notification tools are available
+HAS_NOTIFY=false
+if command -v notify-send &> /dev/null; then
+    HAS_NOTIFY=true
+elif command -v osascript &> /dev/null; then
+    HAS_NOTIFY=true
+fi
+
+# Send a notification; the text is passed as arguments, never as code
+function notify() {
+    if [ "$HAS_NOTIFY" = true ]; then
+        if command -v notify-send &> /dev/null; then
+            notify-send -- "$1" "$2" || true
+        elif command -v osascript &> /dev/null; then
+            osascript -e 'on run argv' \
+                -e 'display notification (item 2 of argv) with title (item 1 of argv)' \
+                -e 'end run' "$1" "$2" || true
+        fi
+    else
+        printf '%s: %s\n' "$1" "$2"
+    fi
+}
+
+# Launch a task's command, or with ZIBOX_DRY_RUN=1 show what would run
+function run_action() {
+    if [ "${ZIBOX_DRY_RUN:-}" = 1 ]; then
+        printf 'Would run:'
+        printf ' %q' "$@"
+        printf '\n'
+    else
+        "$@" &
+    fi
+}
+"#;
+
+const POSIX_HEADER: &str = r#"set -eu
+
+# Send a notification, or print it where there's no desktop to send it to;
+# the text is passed as arguments, never as code
+notify() {
+    if command -v notify-send > /dev/null 2>&1; then
+        notify-send -- "$1" "$2" || true
+    elif command -v osascript > /dev/null 2>&1; then
+        osascript -e 'on run argv' \
+            -e 'display notification (item 2 of argv) with title (item 1 of argv)' \
+            -e 'end run' "$1" "$2" || true
+    else
+        printf '%s: %s\n' "$1" "$2"
+    fi
+}
+
+# Print a word single-quoted, the way it would be typed: each ' becomes '\''
+print_word() {
+    zibox_rest=$1
+    zibox_quoted=
+    while :; do
+        case $zibox_rest in
+            *\'*)
+                zibox_head=${zibox_rest%%\'*}
+                zibox_quoted=$zibox_quoted$zibox_head"'\\''"
+                zibox_rest=${zibox_rest#*\'}
+                ;;
+            *) break ;;
+        esac
+    done
+    printf " '%s%s'" "$zibox_quoted" "$zibox_rest"
+}
+
+# Launch a task's command, or with ZIBOX_DRY_RUN=1 show what would run
+run_action() {
+    if [ "${ZIBOX_DRY_RUN:-}" = 1 ]; then
+        printf 'Would run:'
+        for word in "$@"; do
+            print_word "$word"
+        done
+        printf '\n'
+    else
+        "$@" &
+    fi
+}
+"#;
+
+const POWERSHELL_HEADER: &str = r#"Set-StrictMode -Version Latest
+$ErrorActionPreference = 'Stop'
+
+# Send a toast on Windows (with the BurntToast module) or a notification on
+# macOS and Linux, or print it; the text is passed as arguments, never as code
+function Send-Notification([string]$Title, [string]$Message) {
+    try {
+        if (Get-Module -ListAvailable -Name BurntToast) {
+            Import-Module BurntToast
+            New-BurntToastNotification -Text $Title, $Message
+        } elseif ((Test-Path variable:IsMacOS) -and $IsMacOS) {
+            osascript -e 'on run argv' `
+                -e 'display notification (item 2 of argv) with title (item 1 of argv)' `
+                -e 'end run' $Title $Message
+        } elseif (Get-Command notify-send -ErrorAction SilentlyContinue) {
+            notify-send -- $Title $Message
+        } else {
+            Write-Host "${Title}: $Message"
+        }
+    } catch {
+        Write-Host "${Title}: $Message"
+    }
+}
+
+# Launch a task's command, or with ZIBOX_DRY_RUN=1 show what would run
+function Invoke-Action([string]$Program, [string[]]$Arguments = @()) {
+    if ($env:ZIBOX_DRY_RUN -eq '1') {
+        # Single-quoted as they'd be typed, with quotes inside doubled
+        $words = (@($Program) + $Arguments) | ForEach-Object { "'" + ($_ -replace '([''\u2018-\u201B])', '$1$1') + "'" }
+        Write-Host ('Would run: ' + ($words -join ' '))
+        return
+    }
+    # Start-Process joins its arguments with spaces, so each one is quoted
+    $quoted = @($Arguments | ForEach-Object { '"' + ($_ -replace '(\\*)"', '$1$1\"' -replace '(\\+)$', '$1$1') + '"' })
+    if ($quoted.Count -gt 0) {
+        Start-Process -FilePath $Program -ArgumentList $quoted
+    } else {
+        Start-Process -FilePath $Program
+    }
+}
+"#;
+
+/// How a script language prints, notifies, launches commands and waits
+trait Dialect {
+    fn shebang(&self) -> &'static str;
+    fn header(&self) -> &'static str;
+    /// A value as a single literal word
+    fn quote(&self, value: &str) -> String;
+    fn say(&self, text: &str) -> String;
+    fn notify(&self, title: &str, message: &str) -> String;
+    fn run(&self, command: &[String]) -> String;
+    fn sleep(&self, seconds: u64) -> String;
+}
+
+struct Bash;
+
+/// Plain sh for dash, busybox and the like: the same words as bash, minus
+/// the bashisms, which only live in the header
+struct Posix;
+
+struct PowerShell;
+
+impl Dialect for Bash {
+    fn shebang(&self) -> &'static str {
+        "#!/bin/bash"
+    }
+
+    fn header(&self) -> &'static str {
+        BASH_HEADER
+    }
+
+    fn quote(&self, value: &str) -> String {
+        quote(value)
+    }
+
+    fn say(&self, text: &str) -> String {
+        format!("printf '%s\\n' {}", quote(text))
+    }
+
+    fn notify(&self, title: &str, message: &str) -> String {
+        format!("notify {} {}", quote(title), quote(message))
+    }
+
+    fn run(&self, command: &[String]) -> String {
+        let words: Vec<String> = command.iter().map(|word| quote(word)).collect();
+        format!("run_action {}", words.join(" "))
+    }
+
+    fn sleep(&self, seconds: u64) -> String {
+        format!("sleep {}", seconds)
+    }
+}
+
+impl Dialect for Posix {
+    fn shebang(&self) -> &'static str {
+        "#!/bin/sh"
+    }
+
+    fn header(&self) -> &'static str {
+        POSIX_HEADER
+    }
+
+    fn quote(&self, value: &str) -> String {
+        Bash.quote(value)
+    }
+
+    fn say(&self, text: &str) -> String {
+        Bash.say(text)
+    }
+
+    fn notify(&self, title: &str, message: &str) -> String {
+        Bash.notify(title, message)
+    }
+
+    fn run(&self, command: &[String]) -> String {
+        Bash.run(command)
+    }
+
+    fn sleep(&self, seconds: u64) -> String {
+        Bash.sleep(seconds)
+    }
+}
+
+impl Dialect for PowerShell {
+    fn shebang(&self) -> &'static str {
+        "#!/usr/bin/env pwsh"
+    }
+
+    fn header(&self) -> &'static str {
+        POWERSHELL_HEADER
+    }
+
+    /// Single-quoted strings are literal and a quote inside is doubled.
+    /// PowerShell takes the curly single quotes as quotes too, so they're
+    /// doubled as well.
+    fn quote(&self, value: &str) -> String {
+        let mut quoted = String::from("'");
+        for c in value.chars() {
+            if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+                quoted.push(c);
+            }
+            quoted.push(c);
+        }
+        quoted.push('\'');
+        quoted
+    }
+
+    fn say(&self, text: &str) -> String {
+        format!("Write-Host {}", self.quote(text))
+    }
+
+    fn notify(&self, title: &str, message: &str) -> String {
+        format!("Send-Notification {} {}", self.quote(title), self.quote(message))
+    }
+
+    fn run(&self, command: &[String]) -> String {
+        let (program, arguments) = command.split_first().expect("commands have a program");
+        if arguments.is_empty() {
+            return format!("Invoke-Action {}", self.quote(program));
+        }
+        let arguments: Vec<String> = arguments.iter().map(|word| self.quote(word)).collect();
+        format!("Invoke-Action {} @({})", self.quote(program), arguments.join(", "))
+    }
+
+    fn sleep(&self, seconds: u64) -> String {
+        format!("Start-Sleep -Seconds {}", seconds)
+    }
+}
+
+pub fn emit_shell_script(program: &IRProgram, actions: &Actions) -> Result<String> {
+    emit_script(program, actions, &Bash)
+}
+
+pub fn emit_posix_script(program: &IRProgram, actions: &Actions) -> Result<String> {
+    emit_script(program, actions, &Posix)
+}
+
+pub fn emit_powershell_script(program: &IRProgram, actions: &Actions) -> Result<String> {
+    emit_script(program, actions, &PowerShell)
+}
+
+fn emit_script(program: &IRProgram, actions: &Actions, dialect: &dyn Dialect) -> Result<String> {
+    let mut output = String::new();
+
+    writeln!(&mut output, "{}", dialect.shebang())?;
+    writeln!(&mut output, "# Generated by Zero Inbox")?;
+    writeln!(&mut output, "# Date: {}", Local::now().format("%Y-%m-%d %H:%M:%S"))?;
+    writeln!(&mut output, "{}", dialect.header())?;
+
+    for block in &program.blocks {
+        writeln!(&mut output, "{}", dialect.say(&format!("[{}]", block.name)))?;
+
+        for task in &block.tasks {
+            let task_name = task.display_name();
+            let duration = task.duration_minutes();
+            let start_time = match task.scheduled_start {
+                Some(start) => start.format("%H:%M").to_string(),
+                None => "??:??".to_string(),
+            };
+
+            writeln!(&mut output, "# Task: {} ({})", comment(&task_name), format_duration(duration))?;
+            writeln!(&mut output, "{}", dialect.say(&format!("[{}] Starting: {}", start_time, task_name)))?;
+            writeln!(&mut output, "{}", dialect.notify("Task Start", &task_name))?;
+            match actions.command(task) {
+                Some(Ok(command)) => writeln!(&mut output, "{}", dialect.run(&command))?,
+                Some(Err(e)) => writeln!(&mut output, "# Not run: {}", comment(&e.to_string()))?,
+                None => {},
+            }
+
+            // If the task has a duration, wait it out
+            if duration > 0 {
+                writeln!(&mut output, "{}", dialect.say(&format!("Waiting for {} minutes...", duration)))?;
+                writeln!(&mut output, "{}", dialect.sleep(duration * 60))?;
+            }
+
+            writeln!(&mut output, "{}", dialect.say(&format!("Completed: {}", task_name)))?;
+            writeln!(&mut output, "{}", dialect.notify("Task Complete", &task_name))?;
+            writeln!(&mut output)?;
+        }
+    }
+
+    writeln!(&mut output, "{}", dialect.say("All tasks completed!"))?;
+    writeln!(&mut output, "{}", dialect.notify("Zero Inbox", "All tasks completed!"))?;
+
+    Ok(output)
+}
+
+/// Quote a value as a single shell word. Inside single quotes nothing is
+/// special, so the only thing to take care of is a single quote itself,
+/// which closes the string, adds an escaped quote and reopens it.
+pub(super) fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Comments end at a line break, so control characters are blanked out.
+/// `#` starts a comment in all three languages.
+fn comment(text: &str) -> String {
+    text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
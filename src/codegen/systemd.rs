@@ -0,0 +1,184 @@
+//! systemd user timers that send a notification as each task starts.
+//!
+//! Every scheduled task that isn't done gets a `.timer` that fires at its
+//! start and a oneshot `.service` that runs `notify-send`, both named after
+//! the task ID. A `zibox-plan.target` wants all the timers, so the whole
+//! plan starts with one `systemctl --user start zibox-plan.target`.
+//!
+//! Every unit starts with a `# Generated by Zero Inbox` line. Writing a plan
+//! into a directory removes the units with that line which the new plan
+//! doesn't have, so tasks that are gone don't fire; units written by hand
+//! are left alone. Timers that are already running need stopping too, which
+//! only systemctl can do, so the caller gets the command to run.
+
+use crate::ir::{IRProgram, IRTask};
+use crate::utils::ensure_dir_exists;
+use anyhow::{Result, anyhow};
+use log::info;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+const TARGET: &str = "zibox-plan.target";
+
+/// First line of every unit we write, so we only ever remove our own
+const MARKER: &str = "# Generated by Zero Inbox";
+
+struct Unit {
+    name: String,
+    contents: String,
+}
+
+/// What [`write_systemd_units`] did to the directory
+#[derive(Debug, Clone, Default)]
+pub struct SystemdUnits {
+    pub written: Vec<PathBuf>,
+    /// Units an earlier plan wrote that this one no longer has
+    pub removed: Vec<PathBuf>,
+}
+
+impl SystemdUnits {
+    /// The systemctl command that makes systemd catch up: stop the removed
+    /// units, reload, and restart the target so changed timers take effect
+    pub fn apply_command(&self) -> String {
+        let mut steps = Vec::new();
+        let removed: Vec<String> = self.removed.iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+        if !removed.is_empty() {
+            steps.push(format!("systemctl --user stop {}", removed.join(" ")));
+        }
+        steps.push("systemctl --user daemon-reload".to_string());
+        steps.push(format!("systemctl --user restart {}", TARGET));
+        steps.join(" && ")
+    }
+}
+
+/// All units on stdout, each under a comment naming its file
+pub fn emit_systemd(program: &IRProgram) -> Result<String> {
+    let mut output = String::new();
+    for unit in units(program)? {
+        writeln!(&mut output, "# {}", unit.name)?;
+        writeln!(&mut output, "{}", unit.contents)?;
+    }
+    Ok(output)
+}
+
+/// Write the units into `dir` (usually `~/.config/systemd/user`) and remove
+/// the generated ones this plan doesn't have
+pub fn write_systemd_units(program: &IRProgram, dir: &Path) -> Result<SystemdUnits> {
+    ensure_dir_exists(dir)?;
+    let units = units(program)?;
+    let removed = remove_stale_units(dir, &units)?;
+    let mut written = Vec::new();
+    for unit in units {
+        let path = dir.join(&unit.name);
+        std::fs::write(&path, &unit.contents)
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+        written.push(path);
+    }
+    Ok(SystemdUnits { written, removed })
+}
+
+/// Only `zibox-*.service` and `.timer` files that start with our marker are
+/// touched; the target is rewritten anyway
+fn remove_stale_units(dir: &Path, units: &[Unit]) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| anyhow!("Failed to read {}: {}", dir.display(), e))?;
+    let mut removed = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| anyhow!("Failed to read {}: {}", dir.display(), e))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let ours = name.starts_with("zibox-") && (name.ends_with(".service") || name.ends_with(".timer"));
+        if !ours || units.iter().any(|unit| unit.name == name) {
+            continue;
+        }
+        let path = entry.path();
+        let generated = std::fs::read_to_string(&path)
+            .is_ok_and(|contents| contents.lines().next() == Some(MARKER));
+        if !generated {
+            continue;
+        }
+        std::fs::remove_file(&path)
+            .map_err(|e| anyhow!("Failed to remove {}: {}", path.display(), e))?;
+        info!("Removed stale unit {}", path.display());
+        removed.push(path);
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+fn units(program: &IRProgram) -> Result<Vec<Unit>> {
+    let tasks: Vec<&IRTask> = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .filter(|t| t.scheduled_start.is_some() && !t.completed)
+        .collect();
+    if tasks.is_empty() {
+        return Err(anyhow!("Nothing to schedule: no task is scheduled"));
+    }
+
+    let mut units = Vec::new();
+    let mut timers = Vec::new();
+    for task in tasks {
+        let Some(start) = task.scheduled_start else { continue };
+        let name = format!("zibox-{}", unit_id(&task.id));
+        let description = value(&format!("Zero Inbox: {}", task.display_name()));
+
+        let mut service = String::new();
+        writeln!(&mut service, "{}", MARKER)?;
+        writeln!(&mut service, "[Unit]")?;
+        writeln!(&mut service, "Description={}", description)?;
+        writeln!(&mut service)?;
+        writeln!(&mut service, "[Service]")?;
+        writeln!(&mut service, "Type=oneshot")?;
+        writeln!(
+            &mut service,
+            "ExecStart=/usr/bin/env notify-send -- {} {}",
+            argument("Task Start"),
+            argument(&task.display_name())
+        )?;
+        units.push(Unit { name: format!("{}.service", name), contents: service });
+
+        let mut timer = String::new();
+        writeln!(&mut timer, "{}", MARKER)?;
+        writeln!(&mut timer, "[Unit]")?;
+        writeln!(&mut timer, "Description={}", description)?;
+        writeln!(&mut timer, "PartOf={}", TARGET)?;
+        writeln!(&mut timer)?;
+        writeln!(&mut timer, "[Timer]")?;
+        writeln!(&mut timer, "OnCalendar={}", start.format("%Y-%m-%d %H:%M:00"))?;
+        writeln!(&mut timer, "AccuracySec=1s")?;
+        timers.push(format!("{}.timer", name));
+        units.push(Unit { name: format!("{}.timer", name), contents: timer });
+    }
+
+    let mut target = String::new();
+    writeln!(&mut target, "{}", MARKER)?;
+    writeln!(&mut target, "[Unit]")?;
+    writeln!(&mut target, "Description=Zero Inbox plan")?;
+    writeln!(&mut target, "Wants={}", timers.join(" "))?;
+    units.push(Unit { name: TARGET.to_string(), contents: target });
+
+    Ok(units)
+}
+
+/// Unit names take letters, digits and `_-.:`; task IDs rarely need more
+fn unit_id(id: &str) -> String {
+    id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' }).collect()
+}
+
+/// Settings end at a line break, and `%` starts a specifier
+fn value(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .replace('%', "%%")
+}
+
+/// A single argument on an `ExecStart=` line: double-quoted, with
+/// backslashes and quotes escaped, and `$` doubled so it isn't expanded
+fn argument(text: &str) -> String {
+    let escaped = value(text).replace('\\', "\\\\").replace('"', "\\\"").replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
@@ -0,0 +1,190 @@
+//! Taskwarrior's JSON, as read by `task import` and written by `task export`.
+//!
+//! Blocks are projects, priorities are `H`, `M` and `L`, and durations go in
+//! an `estimate` UDA as ISO 8601 (`PT1H30M`). Critical counts as `H`, with a
+//! `zibox_priority` UDA saying what it was. Taskwarrior has no subtasks, so
+//! they're written as tasks of their own that their parent depends on, and
+//! that name it in a `zibox_parent` UDA. A due date is `due`, at midnight,
+//! and the scheduled slot is `scheduled`. Notes and links become
+//! annotations. Every task gets a UUID derived from its ID, or keeps the one
+//! it was imported with, so importing a plan again updates the same tasks.
+
+use super::source_name;
+use crate::ast::Priority;
+use crate::ir::{IRProgram, IRTask};
+use crate::utils::stable_hash;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+
+/// The fields zibox reads and writes; Taskwarrior's others are ignored
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaskwarriorTask {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    #[serde(default, deserialize_with = "depends", skip_serializing_if = "Vec::is_empty")]
+    pub depends: Vec<String>,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<String>,
+    #[serde(default = "default_status")]
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub uuid: String,
+    /// UDA: the UUID of the task this one is a subtask of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zibox_parent: Option<String>,
+    /// UDA: `critical`, which `priority` can't say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zibox_priority: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Annotation {
+    pub description: String,
+    pub entry: String,
+}
+
+const TIMESTAMP: &str = "%Y%m%dT%H%M%SZ";
+
+fn default_status() -> String {
+    "pending".to_string()
+}
+
+/// Taskwarrior 2.6 and later write `depends` as an array; older releases as
+/// a comma-separated string
+fn depends<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Depends {
+        List(Vec<String>),
+        Joined(String),
+    }
+    Ok(match Depends::deserialize(deserializer)? {
+        Depends::List(uuids) => uuids,
+        Depends::Joined(uuids) => uuids.split(',').filter(|u| !u.is_empty()).map(String::from).collect(),
+    })
+}
+
+pub fn emit_taskwarrior(program: &IRProgram) -> Result<String> {
+    let tasks: Vec<&IRTask> = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .collect();
+    let parents: HashMap<&str, &str> = tasks.iter()
+        .flat_map(|t| t.subtasks.iter().map(|s| (s.id.as_str(), t.id.as_str())))
+        .collect();
+    let uuids: HashMap<&str, String> = tasks.iter()
+        .map(|t| (t.id.as_str(), task_uuid(&t.id)))
+        .collect();
+    let now = timestamp(Local::now());
+
+    let exported: Vec<TaskwarriorTask> = tasks.iter()
+        .map(|task| {
+            // A parent waits for its subtasks, so it can't be done before them
+            let depends = task.depends_on.iter()
+                .chain(task.subtasks.iter().map(|s| &s.id))
+                .filter_map(|id| uuids.get(id.as_str()).cloned())
+                .collect();
+
+            let annotations = task.notes.iter()
+                .chain(&task.link)
+                .map(|text| Annotation { description: text.clone(), entry: now.clone() })
+                .collect();
+
+            let mut tags: Vec<String> = task.tags.iter().cloned().collect();
+            tags.sort();
+
+            TaskwarriorTask {
+                annotations,
+                depends,
+                description: source_name(task),
+                due: task.due
+                    .and_then(|due| due.and_time(NaiveTime::MIN).and_local_timezone(Local).earliest())
+                    .map(timestamp),
+                estimate: task.duration.as_ref().map(|d| iso_duration(d.minutes)),
+                priority: Some(priority_letter(&task.priority).to_string()),
+                project: Some(task.block.clone()),
+                scheduled: task.scheduled_start.map(timestamp),
+                status: if task.completed { "completed" } else { "pending" }.to_string(),
+                tags,
+                uuid: uuids[task.id.as_str()].clone(),
+                zibox_parent: parents.get(task.id.as_str()).map(|parent| uuids[parent].clone()),
+                zibox_priority: (task.priority == Priority::Critical).then(|| "critical".to_string()),
+            }
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&exported)
+        .map_err(|e| anyhow!("JSON serialization error: {}", e))
+}
+
+/// The task's ID if it's already a UUID, otherwise one derived from it
+fn task_uuid(id: &str) -> String {
+    if is_uuid(id) {
+        return id.to_lowercase();
+    }
+    let high = stable_hash(id);
+    let low = stable_hash(&format!("zibox\u{0}{}", id));
+    // RFC 9562 version 8 (custom), variant 10
+    let high = (high & !0xf000) | 0x8000;
+    let low = (low & !(0b11 << 62)) | (0b10 << 62);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+/// `8-4-4-4-12` hex digits
+fn is_uuid(text: &str) -> bool {
+    let groups: Vec<&str> = text.split('-').collect();
+    groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(group, len)| {
+            group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit())
+        })
+}
+
+fn priority_letter(priority: &Priority) -> char {
+    match priority {
+        Priority::Critical | Priority::High => 'H',
+        Priority::Medium => 'M',
+        Priority::Low => 'L',
+    }
+}
+
+/// Taskwarrior's UTC timestamps, `20261016T090000Z`
+fn timestamp<Tz: TimeZone>(time: DateTime<Tz>) -> String {
+    time.with_timezone(&Utc).format(TIMESTAMP).to_string()
+}
+
+/// A timestamp as Taskwarrior writes it, or in RFC 3339
+pub fn parse_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(text, TIMESTAMP)
+        .map(|time| time.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(text).map(|time| time.with_timezone(&Utc)))
+        .ok()
+}
+
+/// `PT1H30M`; whole days are left as hours, as Taskwarrior writes them
+fn iso_duration(minutes: u64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("PT{}M", minutes),
+        (hours, 0) => format!("PT{}H", hours),
+        (hours, minutes) => format!("PT{}H{}M", hours, minutes),
+    }
+}
@@ -0,0 +1,626 @@
+//! Output from a user's template, for reports zibox has no format for.
+//!
+//! The language is a small subset of Jinja:
+//!
+//! ```text
+//! # {{ now | time: "%A %d %B" }}
+//! {% for block in blocks %}
+//! ## {{ block.name | upper }} ({{ block.duration | duration }})
+//! {% for task in block.tasks %}
+//! - [{% if task.done %}x{% else %} {% endif %}] {{ task.start | time }} {{ task.title }}
+//! {% endfor %}
+//! {% endfor %}
+//! ```
+//!
+//! `{{ ... }}` prints a value, `{% ... %}` is a `for`, `if`/`elif`/`else` or
+//! their `end`, and `{# ... #}` is a comment. A tag or comment alone on its
+//! line takes the whole line with it, so templates can be laid out like the
+//! text they produce. Unknown variables, fields and filters are errors rather
+//! than blanks, so typos show up straight away.
+
+use crate::ast::Priority;
+use crate::ir::{IRBlock, IRProgram, IRTask};
+use crate::utils::format_duration;
+use anyhow::{Result, anyhow};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+
+pub fn render_template(program: &IRProgram, source: &str) -> Result<String> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let (nodes, end) = parser.nodes(&[])?;
+    if let Some((tag, line)) = end {
+        return Err(error(line, format!("Unexpected {{% {} %}}", tag)));
+    }
+
+    let mut output = String::new();
+    let mut scopes = vec![("now".to_string(), Value::Time(Local::now()))];
+    scopes.extend(program_values(program));
+    render(&nodes, &mut scopes, &mut output)?;
+    Ok(output)
+}
+
+fn error(line: usize, message: impl std::fmt::Display) -> anyhow::Error {
+    anyhow!("Template error on line {}: {}", line, message)
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(i64),
+    Text(String),
+    Time(DateTime<Local>),
+    List(Vec<Value>),
+    Object(BTreeMap<&'static str, Value>),
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0,
+            Value::Text(s) => !s.is_empty(),
+            Value::Time(_) => true,
+            Value::List(items) => !items.is_empty(),
+            Value::Object(_) => true,
+        }
+    }
+
+    fn text(&self) -> Result<String> {
+        Ok(match self {
+            Value::Null => String::new(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Text(s) => s.clone(),
+            Value::Time(t) => t.format("%Y-%m-%d %H:%M").to_string(),
+            Value::List(items) => items.iter().map(Value::text).collect::<Result<Vec<_>>>()?.join(", "),
+            Value::Object(_) => return Err(anyhow!("Can't print an object; pick one of its fields")),
+        })
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "nothing",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::Text(_) => "text",
+            Value::Time(_) => "a time",
+            Value::List(_) => "a list",
+            Value::Object(_) => "an object",
+        }
+    }
+
+    fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Text(a), Value::Text(b)) => a == b,
+            (Value::Time(a), Value::Time(b)) => a == b,
+            (Value::List(a), Value::List(b)) => a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.equals(y)),
+            _ => false,
+        }
+    }
+}
+
+// What templates see of the plan
+
+fn program_values(program: &IRProgram) -> Vec<(String, Value)> {
+    let blocks = program.blocks.iter().map(block_value).collect();
+    let tasks = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .map(task_value)
+        .collect();
+    let minutes: u64 = program.blocks.iter().flat_map(|b| b.tasks.iter()).map(IRTask::duration_minutes).sum();
+    vec![
+        ("blocks".to_string(), Value::List(blocks)),
+        ("tasks".to_string(), Value::List(tasks)),
+        ("duration".to_string(), Value::Number(minutes as i64)),
+    ]
+}
+
+fn block_value(block: &IRBlock) -> Value {
+    let minutes: u64 = block.tasks.iter().map(IRTask::duration_minutes).sum();
+    let mut fields = BTreeMap::new();
+    fields.insert("name", Value::Text(block.name.clone()));
+    fields.insert("tasks", Value::List(block.tasks.iter().map(task_value).collect()));
+    fields.insert("duration", Value::Number(minutes as i64));
+    fields.insert("start", time(block.tasks.iter().filter_map(|t| t.scheduled_start).min()));
+    fields.insert("end", time(block.tasks.iter().filter_map(|t| t.scheduled_end).max()));
+    Value::Object(fields)
+}
+
+fn task_value(task: &IRTask) -> Value {
+    let texts = |items: &[String]| Value::List(items.iter().cloned().map(Value::Text).collect());
+    let mut tags: Vec<String> = task.tags.iter().cloned().collect();
+    tags.sort();
+
+    let mut fields = BTreeMap::new();
+    fields.insert("id", Value::Text(task.id.clone()));
+    fields.insert("name", Value::Text(task.name.clone()));
+    fields.insert("params", texts(&task.params));
+    fields.insert("title", Value::Text(task.display_name()));
+    fields.insert("block", Value::Text(task.block.clone()));
+    fields.insert("duration", task.duration.as_ref().map_or(Value::Null, |d| Value::Number(d.minutes as i64)));
+    fields.insert("priority", Value::Text(priority_name(&task.priority).to_string()));
+    fields.insert("tags", texts(&tags));
+    fields.insert("depends_on", texts(&task.depends_on));
+    fields.insert("start", time(task.scheduled_start));
+    fields.insert("end", time(task.scheduled_end));
+    fields.insert("due", task.due.map_or(Value::Null, |due| Value::Text(due.format("%Y-%m-%d").to_string())));
+    fields.insert("done", Value::Bool(task.completed));
+    fields.insert("subtasks", Value::List(task.subtasks.iter().map(task_value).collect()));
+    fields.insert("notes", texts(&task.notes));
+    fields.insert("link", task.link.clone().map_or(Value::Null, Value::Text));
+    fields.insert("run", task.run.clone().map_or(Value::Null, Value::Text));
+    Value::Object(fields)
+}
+
+fn time(time: Option<DateTime<Local>>) -> Value {
+    time.map_or(Value::Null, Value::Time)
+}
+
+fn priority_name(priority: &Priority) -> &'static str {
+    match priority {
+        Priority::Critical => "critical",
+        Priority::High => "high",
+        Priority::Medium => "medium",
+        Priority::Low => "low",
+    }
+}
+
+// Splitting a template into text and tags
+
+enum Token {
+    Text(String),
+    Output(String, usize),
+    Tag(String, usize),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    // Where the text not yet in a token starts
+    let mut start = 0;
+    let mut search = 0;
+    while let Some(found) = source[search..].find('{') {
+        let open = search + found;
+        let (kind, close_marker) = match source[open + 1..].chars().next() {
+            Some('{') => ('{', "}}"),
+            Some('%') => ('%', "%}"),
+            Some('#') => ('#', "#}"),
+            _ => {
+                search = open + 1;
+                continue;
+            },
+        };
+        let line = source[..open].matches('\n').count() + 1;
+        let close = source[open + 2..].find(close_marker)
+            .map(|c| open + 2 + c)
+            .ok_or_else(|| error(line, format!("{} is never closed with {}", &source[open..open + 2], close_marker)))?;
+        let inner = source[open + 2..close].trim().to_string();
+
+        // A tag or comment alone on its line swallows the line
+        let mut text_end = open;
+        let mut next = close + 2;
+        if kind != '{' {
+            let line_start = source[..open].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = source[next..].find('\n').map_or(source.len(), |i| next + i);
+            if line_start >= start && source[line_start..open].trim().is_empty() && source[next..line_end].trim().is_empty() {
+                text_end = line_start;
+                next = (line_end + 1).min(source.len());
+            }
+        }
+
+        if text_end > start {
+            tokens.push(Token::Text(source[start..text_end].to_string()));
+        }
+        match kind {
+            '{' => tokens.push(Token::Output(inner, line)),
+            '%' => tokens.push(Token::Tag(inner, line)),
+            _ => {},
+        }
+        start = next;
+        search = next;
+    }
+    if start < source.len() {
+        tokens.push(Token::Text(source[start..].to_string()));
+    }
+    Ok(tokens)
+}
+
+// Parsing tags into a tree
+
+enum Node {
+    Text(String),
+    Output(Expr, usize),
+    For { name: String, items: Expr, body: Vec<Node>, line: usize },
+    If { branches: Vec<(Expr, Vec<Node>)>, otherwise: Vec<Node>, line: usize },
+}
+
+/// The tag that ended a run of nodes, and its line
+type End = Option<(String, usize)>;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    /// Nodes up to the first tag named in `ends`, which is returned with its
+    /// line. `None` means the template ran out.
+    fn nodes(&mut self, ends: &[&str]) -> Result<(Vec<Node>, End)> {
+        let mut nodes = Vec::new();
+        while self.pos < self.tokens.len() {
+            let token = std::mem::replace(&mut self.tokens[self.pos], Token::Text(String::new()));
+            self.pos += 1;
+            match token {
+                Token::Text(text) => nodes.push(Node::Text(text)),
+                Token::Output(expr, line) => nodes.push(Node::Output(expression(&expr, line)?, line)),
+                Token::Tag(tag, line) => {
+                    let (keyword, rest) = tag.split_once(char::is_whitespace).unwrap_or((&tag, ""));
+                    if ends.contains(&keyword) {
+                        return Ok((nodes, Some((tag, line))));
+                    }
+                    match keyword {
+                        "for" => nodes.push(self.for_loop(rest.trim(), line)?),
+                        "if" => nodes.push(self.condition(rest.trim(), line)?),
+                        "endfor" | "endif" | "elif" | "else" => {
+                            return Err(error(line, format!("Unexpected {{% {} %}}", tag)));
+                        },
+                        _ => return Err(error(line, format!("Unknown tag: {}", keyword))),
+                    }
+                },
+            }
+        }
+        Ok((nodes, None))
+    }
+
+    fn for_loop(&mut self, rest: &str, line: usize) -> Result<Node> {
+        let (name, items) = rest.split_once(" in ")
+            .ok_or_else(|| error(line, "A loop reads {% for item in list %}"))?;
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(error(line, format!("Invalid loop variable: {}", name)));
+        }
+        let items = expression(items, line)?;
+        let (body, end) = self.nodes(&["endfor"])?;
+        if end.is_none() {
+            return Err(error(line, "{% for %} without {% endfor %}"));
+        }
+        Ok(Node::For { name: name.to_string(), items, body, line })
+    }
+
+    fn condition(&mut self, rest: &str, line: usize) -> Result<Node> {
+        let mut branches = Vec::new();
+        let mut test = expression(rest, line)?;
+        loop {
+            let (body, end) = self.nodes(&["elif", "else", "endif"])?;
+            branches.push((test, body));
+            let Some((tag, tag_line)) = end else {
+                return Err(error(line, "{% if %} without {% endif %}"));
+            };
+            match tag.split_once(char::is_whitespace) {
+                Some(("elif", rest)) => test = expression(rest, tag_line)?,
+                _ if tag == "else" => {
+                    let (otherwise, end) = self.nodes(&["endif"])?;
+                    if end.is_none() {
+                        return Err(error(line, "{% if %} without {% endif %}"));
+                    }
+                    return Ok(Node::If { branches, otherwise, line });
+                },
+                _ if tag == "endif" => return Ok(Node::If { branches, otherwise: Vec::new(), line }),
+                _ => return Err(error(tag_line, format!("Unexpected {{% {} %}}", tag))),
+            }
+        }
+    }
+}
+
+// Expressions: `task.start | time: "%H:%M"`, `not task.done and task.tags`
+
+#[derive(Debug)]
+enum Expr {
+    Literal(Value),
+    Path(Vec<String>),
+    Filter(Box<Expr>, String, Option<Value>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Equals(Box<Expr>, Box<Expr>, bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Word {
+    Name(String),
+    Text(String),
+    Number(i64),
+    Pipe,
+    Colon,
+    Equals,
+    NotEquals,
+}
+
+fn words(source: &str, line: usize) -> Result<Vec<Word>> {
+    let mut words = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            },
+            '|' => {
+                chars.next();
+                words.push(Word::Pipe);
+            },
+            ':' => {
+                chars.next();
+                words.push(Word::Colon);
+            },
+            '=' | '!' => {
+                chars.next();
+                if chars.next() != Some('=') {
+                    return Err(error(line, format!("Expected {}= in {}", c, source)));
+                }
+                words.push(if c == '=' { Word::Equals } else { Word::NotEquals });
+            },
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(ch) => text.push(ch),
+                        None => return Err(error(line, format!("Unclosed {} in {}", c, source))),
+                    }
+                }
+                words.push(Word::Text(text));
+            },
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = String::new();
+                number.push(c);
+                chars.next();
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    number.push(d);
+                    chars.next();
+                }
+                let number = number.parse().map_err(|_| error(line, format!("Invalid number: {}", number)))?;
+                words.push(Word::Number(number));
+            },
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&d) = chars.peek().filter(|d| d.is_alphanumeric() || **d == '_' || **d == '.') {
+                    name.push(d);
+                    chars.next();
+                }
+                words.push(Word::Name(name));
+            },
+            c => return Err(error(line, format!("Unexpected {} in {}", c, source))),
+        }
+    }
+    Ok(words)
+}
+
+fn expression(source: &str, line: usize) -> Result<Expr> {
+    let words = words(source, line)?;
+    if words.is_empty() {
+        return Err(error(line, "Empty expression"));
+    }
+    let mut parser = ExprParser { words, pos: 0, line };
+    let expr = parser.or()?;
+    match parser.words.get(parser.pos) {
+        None => Ok(expr),
+        Some(word) => Err(error(line, format!("Unexpected {:?} in {}", word, source))),
+    }
+}
+
+struct ExprParser {
+    words: Vec<Word>,
+    pos: usize,
+    line: usize,
+}
+
+impl ExprParser {
+    fn next_is(&mut self, word: &Word) -> bool {
+        let matches = self.words.get(self.pos) == Some(word);
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.next_is(&Word::Name(keyword.to_string()))
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        let left = self.filtered()?;
+        if self.next_is(&Word::Equals) {
+            return Ok(Expr::Equals(Box::new(left), Box::new(self.filtered()?), false));
+        }
+        if self.next_is(&Word::NotEquals) {
+            return Ok(Expr::Equals(Box::new(left), Box::new(self.filtered()?), true));
+        }
+        Ok(left)
+    }
+
+    fn filtered(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+        while self.next_is(&Word::Pipe) {
+            let Some(Word::Name(filter)) = self.words.get(self.pos).cloned() else {
+                return Err(error(self.line, "Expected a filter name after |"));
+            };
+            self.pos += 1;
+            let argument = if self.next_is(&Word::Colon) {
+                let argument = match self.words.get(self.pos).cloned() {
+                    Some(Word::Text(text)) => Value::Text(text),
+                    Some(Word::Number(n)) => Value::Number(n),
+                    _ => return Err(error(self.line, format!("Expected text or a number after {}:", filter))),
+                };
+                self.pos += 1;
+                Some(argument)
+            } else {
+                None
+            };
+            expr = Expr::Filter(Box::new(expr), filter, argument);
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let word = self.words.get(self.pos).cloned()
+            .ok_or_else(|| error(self.line, "Expression ends too early"))?;
+        self.pos += 1;
+        Ok(match word {
+            Word::Text(text) => Expr::Literal(Value::Text(text)),
+            Word::Number(n) => Expr::Literal(Value::Number(n)),
+            Word::Name(name) => match name.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "none" => Expr::Literal(Value::Null),
+                _ => Expr::Path(name.split('.').map(str::to_string).collect()),
+            },
+            word => return Err(error(self.line, format!("Unexpected {:?}", word))),
+        })
+    }
+}
+
+// Rendering
+
+type Scopes = Vec<(String, Value)>;
+
+fn render(nodes: &[Node], scopes: &mut Scopes, output: &mut String) -> Result<()> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Output(expr, line) => {
+                let text = evaluate(expr, scopes).and_then(|value| value.text()).map_err(|e| error(*line, e))?;
+                output.push_str(&text);
+            },
+            Node::For { name, items, body, line } => {
+                let items = match evaluate(items, scopes).map_err(|e| error(*line, e))? {
+                    Value::List(items) => items,
+                    Value::Null => Vec::new(),
+                    other => return Err(error(*line, format!("Can't loop over {}", other.kind()))),
+                };
+                let count = items.len();
+                for (index, item) in items.into_iter().enumerate() {
+                    let mut state = BTreeMap::new();
+                    state.insert("index", Value::Number(index as i64 + 1));
+                    state.insert("first", Value::Bool(index == 0));
+                    state.insert("last", Value::Bool(index + 1 == count));
+                    scopes.push(("loop".to_string(), Value::Object(state)));
+                    scopes.push((name.clone(), item));
+                    let result = render(body, scopes, output);
+                    scopes.truncate(scopes.len() - 2);
+                    result?;
+                }
+            },
+            Node::If { branches, otherwise, line } => {
+                let mut chosen = otherwise;
+                for (test, body) in branches {
+                    if evaluate(test, scopes).map_err(|e| error(*line, e))?.truthy() {
+                        chosen = body;
+                        break;
+                    }
+                }
+                render(chosen, scopes, output)?;
+            },
+        }
+    }
+    Ok(())
+}
+
+fn evaluate(expr: &Expr, scopes: &Scopes) -> Result<Value> {
+    Ok(match expr {
+        Expr::Literal(value) => value.clone(),
+        Expr::Path(path) => {
+            let mut value = &scopes.iter().rev()
+                .find(|(name, _)| *name == path[0])
+                .ok_or_else(|| anyhow!("Unknown variable: {}", path[0]))?
+                .1;
+            for field in &path[1..] {
+                value = match value {
+                    Value::Object(fields) => fields.get(field.as_str())
+                        .ok_or_else(|| anyhow!("{} has no field {}", path.join("."), field))?,
+                    other => return Err(anyhow!("{} is {}, which has no field {}", path[0], other.kind(), field)),
+                };
+            }
+            value.clone()
+        },
+        Expr::Filter(input, name, argument) => filter(evaluate(input, scopes)?, name, argument.as_ref())?,
+        Expr::Not(inner) => Value::Bool(!evaluate(inner, scopes)?.truthy()),
+        Expr::And(left, right) => Value::Bool(evaluate(left, scopes)?.truthy() && evaluate(right, scopes)?.truthy()),
+        Expr::Or(left, right) => Value::Bool(evaluate(left, scopes)?.truthy() || evaluate(right, scopes)?.truthy()),
+        Expr::Equals(left, right, negate) => {
+            Value::Bool(evaluate(left, scopes)?.equals(&evaluate(right, scopes)?) != *negate)
+        },
+    })
+}
+
+fn filter(value: Value, name: &str, argument: Option<&Value>) -> Result<Value> {
+    let text_argument = |default: &str| -> Result<String> {
+        match argument {
+            None => Ok(default.to_string()),
+            Some(Value::Text(text)) => Ok(text.clone()),
+            Some(other) => Err(anyhow!("The {} filter takes text, not {}", name, other.kind())),
+        }
+    };
+
+    Ok(match (name, value) {
+        ("default", value) => match value.truthy() {
+            true => value,
+            false => argument.cloned().unwrap_or(Value::Null),
+        },
+        ("length", Value::List(items)) => Value::Number(items.len() as i64),
+        ("length", Value::Text(text)) => Value::Number(text.chars().count() as i64),
+        ("length", Value::Null) => Value::Number(0),
+        // Everything else lets nothing through as nothing, so unscheduled
+        // tasks print blank times rather than failing
+        (_, Value::Null) if matches!(name, "duration" | "time" | "upper" | "lower" | "join") => Value::Null,
+        ("duration", Value::Number(minutes)) => Value::Text(format_duration(minutes.max(0) as u64)),
+        ("time", Value::Time(time)) => {
+            let format = text_argument("%H:%M")?;
+            if StrftimeItems::new(&format).any(|item| matches!(item, Item::Error)) {
+                return Err(anyhow!("Invalid time format: {}", format));
+            }
+            Value::Text(time.format(&format).to_string())
+        },
+        ("upper", value) => Value::Text(value.text()?.to_uppercase()),
+        ("lower", value) => Value::Text(value.text()?.to_lowercase()),
+        ("join", Value::List(items)) => {
+            let texts = items.iter().map(Value::text).collect::<Result<Vec<_>>>()?;
+            Value::Text(texts.join(&text_argument(", ")?))
+        },
+        ("duration" | "time" | "join" | "length", value) => {
+            let wants = match name {
+                "duration" => "minutes",
+                "time" => "a time",
+                _ => "a list",
+            };
+            return Err(anyhow!("The {} filter needs {}, not {}", name, wants, value.kind()));
+        },
+        _ => return Err(anyhow!("Unknown filter: {}", name)),
+    })
+}
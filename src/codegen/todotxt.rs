@@ -0,0 +1,115 @@
+//! todo.txt, one line per task.
+//!
+//! Priorities are `(A)` (critical) to `(D)` (low), tags are `+project`s and
+//! a task's block is its `@context`. Durations, IDs and dependencies go in
+//! `dur:`, `id:` and `after:`, a subtask names its parent with `parent:`, a
+//! due date is `due:` and a scheduled task's `t:` is the day it starts. Each
+//! note is a `note:`, with whitespace and `%` percent-encoded so it stays one
+//! word. Completed tasks start with `x` and keep their priority as `pri:`, as
+//! the format suggests.
+
+use super::{duration_literal, source_name};
+use crate::ast::Priority;
+use crate::ir::{self, IRProgram, IRTask};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+pub fn emit_todotxt(program: &IRProgram) -> Result<String> {
+    // Tasks that something refers to by ID need one on their line
+    let referenced: HashSet<&str> = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .flat_map(|t| t.depends_on.iter().map(|d| d.as_str()))
+        .collect();
+
+    let mut output = String::new();
+    for block in &program.blocks {
+        for task in &block.tasks {
+            write_task(&mut output, task, &block.name, None, &referenced)?;
+        }
+    }
+    Ok(output)
+}
+
+fn write_task(
+    output: &mut String,
+    task: &IRTask,
+    block: &str,
+    parent: Option<&str>,
+    referenced: &HashSet<&str>,
+) -> Result<()> {
+    let mut parts = Vec::new();
+    if task.completed {
+        parts.push("x".to_string());
+    } else {
+        parts.push(format!("({})", priority_letter(&task.priority)));
+    }
+    parts.push(source_name(task));
+
+    let mut tags: Vec<&String> = task.tags.iter().collect();
+    tags.sort();
+    parts.extend(tags.into_iter().map(|tag| format!("+{}", tag)));
+    parts.push(format!("@{}", block));
+
+    if task.completed {
+        parts.push(format!("pri:{}", priority_letter(&task.priority)));
+    }
+    // A parent's duration is left out when it's just the sum of its subtasks
+    let derived = task.subtasks.iter().map(|t| t.duration_minutes()).sum::<u64>();
+    if let Some(duration) = &task.duration {
+        if task.subtasks.is_empty() || duration.minutes != derived {
+            parts.push(format!("dur:{}", duration_literal(duration.minutes)));
+        }
+    }
+    if !ir::is_generated_id(&task.id) || !task.subtasks.is_empty() || referenced.contains(task.id.as_str()) {
+        parts.push(format!("id:{}", task.id));
+    }
+    if let Some(parent) = parent {
+        parts.push(format!("parent:{}", parent));
+    }
+    if !task.depends_on.is_empty() {
+        parts.push(format!("after:{}", task.depends_on.join(",")));
+    }
+    if let Some(due) = task.due {
+        parts.push(format!("due:{}", due.format("%Y-%m-%d")));
+    }
+    if let Some(start) = task.scheduled_start {
+        parts.push(format!("t:{}", start.format("%Y-%m-%d")));
+    }
+    if let Some(link) = &task.link {
+        parts.push(format!("link:{}", link));
+    }
+    parts.extend(task.notes.iter().filter(|n| !n.is_empty()).map(|note| format!("note:{}", encode(note))));
+    writeln!(output, "{}", parts.join(" "))?;
+
+    for subtask in &task.subtasks {
+        write_task(output, subtask, block, Some(&task.id), referenced)?;
+    }
+    Ok(())
+}
+
+fn priority_letter(priority: &Priority) -> char {
+    match priority {
+        Priority::Critical => 'A',
+        Priority::High => 'B',
+        Priority::Medium => 'C',
+        Priority::Low => 'D',
+    }
+}
+
+/// Percent-encode whitespace and `%`, so the text is a single word
+fn encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for c in text.chars() {
+        if c.is_whitespace() || c == '%' {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                write!(&mut encoded, "%{:02X}", byte).unwrap();
+            }
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
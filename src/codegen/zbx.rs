@@ -0,0 +1,205 @@
+//! `.zbx` source, the inverse of the parser.
+//!
+//! Output is in the formatter's canonical layout and parses back to the same
+//! tasks, IDs included. Settings that differ from the defaults come first as
+//! `set` lines; the timezone isn't one, since it's always the machine's. With
+//! `annotate`, each task also gets a trailing comment with its scheduled slot
+//! and whether it's done.
+
+use super::{duration_literal, source_name};
+use crate::ast::Priority;
+use crate::ir::{self, IRMetadata, IRProgram, IRTask};
+use crate::syntax;
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+const INDENT: &str = "  ";
+
+pub fn emit_zbx(program: &IRProgram, annotate: bool) -> Result<String> {
+    let references = References::new(program);
+    let mut output = String::new();
+
+    let settings = settings(&program.metadata);
+    for (key, value) in &settings {
+        writeln!(&mut output, "set {} = {}", key, value)?;
+    }
+
+    for (i, block) in program.blocks.iter().enumerate() {
+        if i > 0 || !settings.is_empty() {
+            writeln!(&mut output)?;
+        }
+        writeln!(&mut output, "@{}", block.name)?;
+        for task in &block.tasks {
+            write_task(&mut output, task, 1, &references, annotate)?;
+        }
+    }
+
+    Ok(output)
+}
+
+/// The `set` directives that reproduce `metadata`, leaving out defaults
+fn settings(metadata: &IRMetadata) -> Vec<(&'static str, String)> {
+    let defaults = IRMetadata::default();
+    let mut settings = Vec::new();
+    if metadata.workday_start != defaults.workday_start {
+        settings.push(("workday_start", metadata.workday_start.format("%H:%M").to_string()));
+    }
+    if metadata.workday_end != defaults.workday_end {
+        settings.push(("workday_end", metadata.workday_end.format("%H:%M").to_string()));
+    }
+    if metadata.optimization_level != defaults.optimization_level {
+        settings.push(("optimization_level", metadata.optimization_level.to_string()));
+    }
+    if metadata.max_parallel != defaults.max_parallel {
+        settings.push(("max_parallel", metadata.max_parallel.to_string()));
+    }
+    if metadata.focus_tags != defaults.focus_tags {
+        settings.push(("focus_tags", metadata.focus_tags.join(",")));
+    }
+    settings
+}
+
+/// How each dependency is spelled in `after:`, and which tasks need an
+/// explicit `id:` for their dependents to find them
+struct References {
+    spelling: HashMap<String, String>,
+    labelled: HashSet<String>,
+}
+
+impl References {
+    fn new(program: &IRProgram) -> Self {
+        let tasks: Vec<&IRTask> = program.blocks.iter()
+            .flat_map(|b| b.tasks.iter())
+            .flat_map(|t| t.flatten())
+            .collect();
+
+        let ids: HashSet<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+        // The parser resolves a name to the first task carrying it
+        let mut by_name = HashMap::new();
+        let mut by_display_name = HashMap::new();
+        for task in &tasks {
+            by_name.entry(task.name.clone()).or_insert(task.id.as_str());
+            by_display_name.entry(source_name(task)).or_insert(task.id.as_str());
+        }
+
+        let mut references = Self { spelling: HashMap::new(), labelled: HashSet::new() };
+        let targets: HashSet<&str> = tasks.iter()
+            .flat_map(|t| t.depends_on.iter().map(|d| d.as_str()))
+            .collect();
+        // A name that's also some task's ID would resolve to that task instead
+        let finds = |spelling: &str, index: &HashMap<String, &str>, task: &IRTask| {
+            index.get(spelling) == Some(&task.id.as_str()) && is_reference(spelling) && !ids.contains(spelling)
+        };
+        for task in tasks.iter().filter(|t| targets.contains(t.id.as_str())) {
+            let spelling = if !ir::is_generated_id(&task.id) {
+                task.id.clone()
+            } else if finds(&task.name, &by_name, task) {
+                task.name.clone()
+            } else if finds(&source_name(task), &by_display_name, task) {
+                source_name(task)
+            } else {
+                // Nothing else finds it, so pin the ID it already has
+                references.labelled.insert(task.id.clone());
+                task.id.clone()
+            };
+            references.spelling.insert(task.id.clone(), spelling);
+        }
+
+        references
+    }
+
+    fn spell<'a>(&'a self, dependency: &'a str) -> &'a str {
+        // Unresolved references stay as they were written
+        self.spelling.get(dependency).map_or(dependency, |s| s.as_str())
+    }
+}
+
+fn write_task(
+    output: &mut String,
+    task: &IRTask,
+    depth: usize,
+    references: &References,
+    annotate: bool,
+) -> Result<()> {
+    let indent = INDENT.repeat(depth);
+    let mut parts = vec![source_name(task)];
+
+    // A parent's duration is left out when it's just the sum of its subtasks
+    let derived = task.subtasks.iter().map(|t| t.duration_minutes()).sum::<u64>();
+    if let Some(duration) = &task.duration {
+        if task.subtasks.is_empty() || duration.minutes != derived {
+            parts.push(format!("[{}]", duration_literal(duration.minutes)));
+        }
+    }
+    if task.priority != Priority::Medium {
+        parts.push(format!("p:{}", format!("{:?}", task.priority).to_lowercase()));
+    }
+    let mut tags: Vec<&String> = task.tags.iter().collect();
+    tags.sort();
+    parts.extend(tags.into_iter().map(|tag| format!("#{}", tag)));
+    if !task.depends_on.is_empty() {
+        let deps: Vec<&str> = task.depends_on.iter().map(|d| references.spell(d)).collect();
+        parts.push(format!("after:{}", deps.join(",")));
+    }
+    if let Some(due) = task.due {
+        parts.push(format!("due:{}", due.format("%Y-%m-%d")));
+    }
+    if !ir::is_generated_id(&task.id) || references.labelled.contains(&task.id) {
+        parts.push(format!("id:{}", task.id));
+    }
+    if let Some(link) = &task.link {
+        parts.push(format!("link:{}", link));
+    }
+    if let Some(run) = &task.run {
+        parts.push(format!("run:{}", syntax::quote(run)));
+    }
+
+    let mut line = format!("{}{}", indent, parts.join(" "));
+    if annotate {
+        if let Some(annotation) = annotation(task) {
+            write!(&mut line, "  # {}", annotation)?;
+        }
+    }
+    writeln!(output, "{}", line)?;
+
+    // Notes sit one level below their task
+    let note_indent = INDENT.repeat(depth + 1);
+    for note in &task.notes {
+        if note.contains('\n') {
+            writeln!(output, "{}\"\"\"", note_indent)?;
+            for note_line in note.lines() {
+                if note_line.is_empty() {
+                    writeln!(output)?;
+                } else {
+                    writeln!(output, "{}{}", note_indent, note_line)?;
+                }
+            }
+            writeln!(output, "{}\"\"\"", note_indent)?;
+        } else {
+            writeln!(output, "{}", format!("{}> {}", note_indent, note).trim_end())?;
+        }
+    }
+
+    for subtask in &task.subtasks {
+        write_task(output, subtask, depth + 1, references, annotate)?;
+    }
+
+    Ok(())
+}
+
+/// Whether `after:` can spell this name (attributes end at whitespace)
+fn is_reference(name: &str) -> bool {
+    !name.is_empty() && !name.contains(char::is_whitespace) && !name.contains(',')
+}
+
+fn annotation(task: &IRTask) -> Option<String> {
+    let mut notes = Vec::new();
+    if let (Some(start), Some(end)) = (task.scheduled_start, task.scheduled_end) {
+        notes.push(format!("{}-{}", start.format("%H:%M"), end.format("%H:%M")));
+    }
+    if task.completed {
+        notes.push("done".to_string());
+    }
+    (!notes.is_empty()).then(|| notes.join(", "))
+}
@@ -1,14 +1,21 @@
+use crate::actions::Actions;
 use crate::ir::IRMetadata;
+use crate::ast::{Priority, Setting};
 use crate::scheduler::ScheduleMode;
-use crate::codegen::OutputFormat;
+use crate::codegen::{CalendarOptions, CalendarTime, OutputFormat};
+use crate::import::InputFormat;
 use chrono::NaiveTime;
 use serde::{Serialize, Deserialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::fs;
 use anyhow::{Result, anyhow};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    /// Input format; guessed from the file extension when unset
+    pub input_format: Option<String>,
     pub output_format: String,
     pub output_file: Option<String>,
     pub workday_start: String,
@@ -21,11 +28,42 @@ pub struct Config {
     pub dry_run: bool,
     pub show_ir: bool,
     pub visualize_schedule: bool,
+    /// Annotate .zbx output with scheduled times and completion
+    pub annotate: bool,
+    /// Lint rule severities by rule ID or name (`off`, `warning`, `error`)
+    pub lint: HashMap<String, String>,
+    /// iCalendar output settings
+    pub calendar: CalendarConfig,
+    /// Commands tasks may launch
+    pub run: RunConfig,
+    /// Template file for the `template` output format
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunConfig {
+    /// Programs that may be run; nothing runs unless it's listed here
+    pub allow: Vec<String>,
+    /// Commands for tasks without `run:`, by task name (`{1}` is the first parameter)
+    pub commands: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalendarConfig {
+    /// How event times are written (`utc`, `floating` or `zoned`); UTC when unset
+    pub time: Option<String>,
+    /// Emit unscheduled tasks as VTODO items
+    pub todos: bool,
+    /// Reminders in minutes before the start, by priority name
+    pub alarms: HashMap<String, u32>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            input_format: None,
             output_format: "markdown".to_string(),
             output_file: None,
             workday_start: "09:00".to_string(),
@@ -38,6 +76,11 @@ impl Default for Config {
             dry_run: false,
             show_ir: false,
             visualize_schedule: false,
+            annotate: false,
+            lint: HashMap::new(),
+            calendar: CalendarConfig::default(),
+            run: RunConfig::default(),
+            template: None,
         }
     }
 }
@@ -77,9 +120,95 @@ impl Config {
         })
     }
     
+    /// Apply `set` directives from a .zbx file on top of this configuration
+    pub fn apply_settings(&mut self, settings: &[Setting]) -> Result<()> {
+        for setting in settings {
+            let value = setting.value.clone();
+            match setting.key.as_str() {
+                "workday_start" => {
+                    parse_time(&value)?;
+                    self.workday_start = value;
+                },
+                "workday_end" => {
+                    parse_time(&value)?;
+                    self.workday_end = value;
+                },
+                "schedule_mode" => {
+                    ScheduleMode::from_str(&value)
+                        .ok_or_else(|| anyhow!("Invalid schedule mode: {}", value))?;
+                    self.schedule_mode = value;
+                },
+                "optimization_level" => {
+                    self.optimization_level = value.parse()
+                        .map_err(|_| anyhow!("Invalid optimization level: {}", value))?;
+                },
+                "max_parallel" => {
+                    self.max_parallel = value.parse()
+                        .map_err(|_| anyhow!("Invalid max_parallel: {}", value))?;
+                },
+                "focus_tags" => {
+                    self.focus_tags = value.split(',')
+                        .map(|t| t.trim().trim_start_matches('#').to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect();
+                },
+                "deepwork_tag" => {
+                    self.deepwork_tag = value;
+                },
+                other => return Err(anyhow!("Unknown setting: {}", other)),
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn get_input_format(&self, path: &Path) -> Result<InputFormat> {
+        match &self.input_format {
+            Some(format) => InputFormat::from_str(format)
+                .ok_or_else(|| anyhow!("Invalid input format: {}", format)),
+            None => Ok(InputFormat::from_path(path)),
+        }
+    }
+    
     pub fn get_output_format(&self) -> Result<OutputFormat> {
-        OutputFormat::from_str(&self.output_format)
-            .ok_or_else(|| anyhow!("Invalid output format: {}", self.output_format))
+        let format = OutputFormat::from_str(&self.output_format)
+            .ok_or_else(|| anyhow!("Invalid output format: {}", self.output_format))?;
+        Ok(match format {
+            OutputFormat::Zbx { .. } => OutputFormat::Zbx { annotate: self.annotate },
+            OutputFormat::Calendar(_) => OutputFormat::Calendar(self.get_calendar_options()?),
+            OutputFormat::Shell(_) => OutputFormat::Shell(self.get_actions()),
+            OutputFormat::Posix(_) => OutputFormat::Posix(self.get_actions()),
+            OutputFormat::PowerShell(_) => OutputFormat::PowerShell(self.get_actions()),
+            OutputFormat::Template(_) => OutputFormat::Template(self.get_template()?),
+            format => format,
+        })
+    }
+    
+    pub fn get_template(&self) -> Result<String> {
+        let path = self.template.as_ref()
+            .ok_or_else(|| anyhow!("The template format needs a template file (--template FILE)"))?;
+        fs::read_to_string(path).map_err(|e| anyhow!("Failed to read template {}: {}", path, e))
+    }
+    
+    pub fn get_actions(&self) -> Actions {
+        Actions {
+            allow: self.run.allow.clone(),
+            commands: self.run.commands.clone(),
+        }
+    }
+    
+    pub fn get_calendar_options(&self) -> Result<CalendarOptions> {
+        let time = match &self.calendar.time {
+            Some(time) => CalendarTime::from_str(time)
+                .ok_or_else(|| anyhow!("Invalid calendar time: {} (use utc, floating or zoned)", time))?,
+            None => CalendarTime::Utc,
+        };
+        let mut alarms = Vec::new();
+        for (priority, &minutes) in &self.calendar.alarms {
+            let priority = priority.to_lowercase().parse::<Priority>()
+                .map_err(|_| anyhow!("Invalid priority for a calendar alarm: {}", priority))?;
+            alarms.push((priority, minutes));
+        }
+        Ok(CalendarOptions { time, todos: self.calendar.todos, alarms })
     }
     
     pub fn get_schedule_mode(&self) -> Result<ScheduleMode> {
@@ -0,0 +1,157 @@
+//! Canonical layout for .zbx source.
+//!
+//! Works on the lossless syntax tree, so comments survive formatting. Blocks
+//! start at column zero, tasks are indented two spaces per nesting level and
+//! attributes are written in a fixed order: duration, priority, tags (sorted),
+//! dependencies, due dates, then labels, links and commands.
+
+use crate::syntax::{self, SyntaxTree, NodeKind, Attribute};
+
+const INDENT: &str = "  ";
+
+pub fn format_source(input: &str) -> String {
+    let tree = SyntaxTree::parse(input);
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_block = false;
+    // Raw indents of the open tasks later lines may nest under, like the parser's
+    let mut open: Vec<usize> = Vec::new();
+
+    for node in &tree.nodes {
+        let base = usize::from(in_block);
+        let mut line = match &node.kind {
+            NodeKind::Blank => {
+                // Collapse runs of blank lines and drop leading ones
+                if lines.last().is_some_and(|l| !l.is_empty()) {
+                    lines.push(String::new());
+                }
+                continue;
+            },
+            NodeKind::Comment(text) => {
+                let level = if node.indent == 0 {
+                    0
+                } else {
+                    base + open.iter().filter(|&&w| w < node.indent).count()
+                };
+                format!("{}{}", INDENT.repeat(level), comment(text))
+            },
+            NodeKind::Block(name) => {
+                open.clear();
+                in_block = true;
+                separate_block(&mut lines);
+                format!("@{}", name)
+            },
+            NodeKind::Let(name, value) => format!("let {} = {}", name, value),
+            NodeKind::Set(key, value) => format!("set {} = {}", key, value),
+            NodeKind::Task { name, attributes } => {
+                while open.last().is_some_and(|&w| w >= node.indent) {
+                    open.pop();
+                }
+                let level = base + open.len();
+                open.push(node.indent);
+
+                let mut parts = vec![name.clone()];
+                parts.extend(canonical_attributes(attributes));
+                format!("{}{}", INDENT.repeat(level), parts.join(" "))
+            },
+            NodeKind::Note { text, quoted } => {
+                // Notes sit one level below the task they belong to
+                let indent = INDENT.repeat(base + open.len());
+                if !quoted {
+                    format!("{}> {}", indent, text).trim_end().to_string()
+                } else if !text.contains('\n') {
+                    format!("{}\"\"\"{}\"\"\"", indent, text)
+                } else {
+                    let mut note = format!("{}\"\"\"", indent);
+                    for note_line in text.lines() {
+                        note.push('\n');
+                        if !note_line.is_empty() {
+                            note.push_str(&indent);
+                            note.push_str(note_line);
+                        }
+                    }
+                    note.push_str(&format!("\n{}\"\"\"", indent));
+                    note
+                }
+            },
+        };
+
+        if let Some(text) = &node.comment {
+            line.push_str("  ");
+            line.push_str(&comment(text));
+        }
+        lines.push(line);
+    }
+
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+
+    let mut output = lines.join("\n");
+    output.push('\n');
+    output
+}
+
+/// Whether `input` is already in canonical layout
+pub fn is_formatted(input: &str) -> bool {
+    format_source(input) == input
+}
+
+fn comment(text: &str) -> String {
+    if text.is_empty() {
+        "#".to_string()
+    } else {
+        format!("# {}", text)
+    }
+}
+
+/// Put a blank line before a block header, above any comments describing it
+fn separate_block(lines: &mut Vec<String>) {
+    let mut at = lines.len();
+    while at > 0 && lines[at - 1].starts_with('#') {
+        at -= 1;
+    }
+    if at > 0 && !lines[at - 1].is_empty() {
+        lines.insert(at, String::new());
+    }
+}
+
+fn canonical_attributes(attributes: &[Attribute]) -> Vec<String> {
+    let rank = |attribute: &Attribute| match attribute {
+        Attribute::Duration(_) => 0,
+        Attribute::Priority(_) => 1,
+        Attribute::Tag(_) => 2,
+        Attribute::DependsOn(_) => 3,
+        Attribute::Due(_) => 4,
+        Attribute::Label(_) => 5,
+        Attribute::Link(_) => 6,
+        Attribute::Run(_) => 7,
+        Attribute::Unknown(_) => 8,
+    };
+
+    let mut sorted: Vec<&Attribute> = attributes.iter().collect();
+    sorted.sort_by(|a, b| {
+        rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+            (Attribute::Tag(a), Attribute::Tag(b)) => a.cmp(b),
+            _ => std::cmp::Ordering::Equal,
+        })
+    });
+    // Tags are a set, so repeating one adds nothing
+    sorted.dedup_by(|a, b| matches!((a, b), (Attribute::Tag(a), Attribute::Tag(b)) if a == b));
+
+    sorted.into_iter()
+        .map(|attribute| match attribute {
+            Attribute::Duration(d) => format!("[{}]", d),
+            Attribute::Priority(p) => format!("p:{}", p.to_lowercase()),
+            Attribute::Tag(t) => format!("#{}", t),
+            Attribute::DependsOn(deps) => format!("after:{}", deps.join(",")),
+            Attribute::Label(l) => format!("id:{}", l),
+            Attribute::Due(d) => format!("due:{}", d),
+            Attribute::Link(l) => format!("link:{}", l),
+            Attribute::Run(r) => format!("run:{}", syntax::quote(r)),
+            Attribute::Unknown(raw) => raw.clone(),
+        })
+        .collect()
+}
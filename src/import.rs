@@ -0,0 +1,140 @@
+//! Reading plans from formats other than .zbx source.
+//!
+//! Every importer produces IR directly, so an imported plan goes through the
+//! same optimizer, scheduler, runtime and emitters as a compiled one.
+
+use crate::ast::{Block, Program, TaskDuration};
+use crate::config::Config;
+use crate::ir::{IRProgram, IRTask};
+use crate::lexer::extract_task_params;
+use crate::utils::slugify;
+use crate::{lexer, parser};
+use anyhow::{Result, anyhow};
+use std::path::Path;
+
+mod csv;
+mod json;
+mod markdown;
+mod taskwarrior;
+mod todotxt;
+
+pub enum InputFormat {
+    Zbx,
+    Json,
+    Markdown,
+    TodoTxt,
+    Taskwarrior,
+    /// CSV, or TSV with a tab delimiter
+    Csv { delimiter: u8 },
+}
+
+impl InputFormat {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "zbx" | "zibox" => Some(InputFormat::Zbx),
+            "json" => Some(InputFormat::Json),
+            "markdown" | "md" => Some(InputFormat::Markdown),
+            "todotxt" | "todo.txt" => Some(InputFormat::TodoTxt),
+            "taskwarrior" | "tw" => Some(InputFormat::Taskwarrior),
+            "csv" => Some(InputFormat::Csv { delimiter: b',' }),
+            "tsv" => Some(InputFormat::Csv { delimiter: b'\t' }),
+            _ => None,
+        }
+    }
+
+    /// Pick a format by file extension; anything unrecognised is .zbx source.
+    /// `todo.txt` and `done.txt` are todo.txt files whatever their extension.
+    pub fn from_path(path: &Path) -> Self {
+        let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if matches!(file_name.to_lowercase().as_str(), "todo.txt" | "done.txt") {
+            return InputFormat::TodoTxt;
+        }
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_str)
+            .unwrap_or(InputFormat::Zbx)
+    }
+}
+
+/// Load a plan in the given format as unscheduled IR.
+///
+/// Returns the effective configuration alongside it, since .zbx source can
+/// override settings with `set`.
+pub fn import(input: &str, format: InputFormat, config: &Config) -> Result<(IRProgram, Config)> {
+    match format {
+        InputFormat::Zbx => {
+            let program = parser::parse_located(&lexer::tokenize(input)).map_err(|e| anyhow!("{}", e))?;
+            crate::lower_program(&program, config)
+        },
+        // `task export` writes an array, where a zibox plan is an object
+        InputFormat::Json if input.trim_start().starts_with('[') => {
+            Ok((taskwarrior::load_taskwarrior(input, config)?, config.clone()))
+        },
+        InputFormat::Json => Ok((json::load_json(input, config)?, config.clone())),
+        InputFormat::Markdown => Ok((markdown::load_markdown(input, config)?, config.clone())),
+        InputFormat::TodoTxt => Ok((todotxt::load_todotxt(input, config)?, config.clone())),
+        InputFormat::Taskwarrior => Ok((taskwarrior::load_taskwarrior(input, config)?, config.clone())),
+        InputFormat::Csv { delimiter } => Ok((csv::load_csv(input, delimiter, config)?, config.clone())),
+    }
+}
+
+/// The block called `name` along with its ticks, added if it's new, for
+/// formats that name a task's block on the task itself
+fn block_named<'a>(blocks: &'a mut Vec<(Block, Vec<bool>)>, name: &str) -> &'a mut (Block, Vec<bool>) {
+    let index = match blocks.iter().position(|(block, _)| block.name == name) {
+        Some(index) => index,
+        None => {
+            blocks.push((Block::new(name.to_string()), Vec::new()));
+            blocks.len() - 1
+        },
+    };
+    &mut blocks[index]
+}
+
+/// Lower blocks built by an importer, then mark tasks done. `completed` has
+/// one entry per task, in the order of a pre-order walk over `blocks`.
+fn lower_blocks(blocks: Vec<Block>, completed: Vec<bool>, config: &Config) -> Result<IRProgram> {
+    let (mut program, _) = crate::lower_program(&Program { blocks, settings: Vec::new() }, config)?;
+
+    // Lowering keeps the tree's order, so ticks line up with a pre-order walk
+    let mut ticks = completed.into_iter();
+    fn tick(task: &mut IRTask, ticks: &mut impl Iterator<Item = bool>) {
+        task.completed = ticks.next().unwrap_or(false);
+        for subtask in &mut task.subtasks {
+            tick(subtask, ticks);
+        }
+    }
+    for task in program.blocks.iter_mut().flat_map(|b| b.tasks.iter_mut()) {
+        tick(task, &mut ticks);
+    }
+    // `to_ir` built the task map before we ticked anything
+    Ok(IRProgram::new(program.blocks, program.metadata))
+}
+
+/// A task name from free text. A single word keeps its `name(params)` shape;
+/// a phrase becomes a slug so the task can still be written as .zbx.
+fn task_name(words: &[&str]) -> (String, Vec<String>) {
+    match words {
+        [word] => extract_task_params(word),
+        _ => (slugify(&words.join(" ")), Vec::new()),
+    }
+}
+
+/// `30m`, `2h` or `1h30m`
+fn parse_duration(text: &str) -> Option<TaskDuration> {
+    let (hours, minutes) = match text.split_once('h') {
+        Some((hours, rest)) => (hours.parse::<u64>().ok()?, rest),
+        None => (0, text),
+    };
+    let minutes = match minutes {
+        "" if text.ends_with('h') => 0,
+        _ => minutes.strip_suffix('m')?.parse::<u64>().ok()?,
+    };
+    Some(TaskDuration::from_minutes(hours * 60 + minutes))
+}
+
+/// Whether a word is a web address, as opposed to a `key:value` pair
+fn is_url(text: &str) -> bool {
+    (text.starts_with("http://") || text.starts_with("https://")) && !text.contains(char::is_whitespace)
+}
@@ -0,0 +1,153 @@
+//! CSV and TSV with a header row, as written by `--output-format csv`.
+//!
+//! Columns are found by name, in any order, and only `block` and `name` are
+//! required; unknown columns are ignored so a spreadsheet can carry its own.
+//! A row with a `parent` becomes a subtask of the row with that `id`, which
+//! must come first. `start` and `end` are left to the scheduler.
+
+use super::{block_named, lower_blocks, parse_duration};
+use crate::ast::{Block, Priority, Task, TaskDuration};
+use crate::config::Config;
+use crate::ir::IRProgram;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+struct Row {
+    task: Task,
+    block: String,
+    completed: bool,
+    subtasks: Vec<usize>,
+}
+
+pub fn load_csv(input: &str, delimiter: u8, config: &Config) -> Result<IRProgram> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(::csv::Trim::All)
+        .from_reader(input.as_bytes());
+
+    let headers = reader.headers().map_err(|e| anyhow!("Invalid CSV: {}", e))?.clone();
+    let columns: HashMap<String, usize> = headers.iter()
+        .enumerate()
+        .map(|(i, name)| (name.to_lowercase(), i))
+        .collect();
+    for required in ["block", "name"] {
+        if !columns.contains_key(required) {
+            return Err(anyhow!("Missing '{}' column", required));
+        }
+    }
+
+    let mut rows: Vec<Row> = Vec::new();
+    let mut roots = Vec::new();
+    let mut by_id = HashMap::new();
+    for (i, record) in reader.records().enumerate() {
+        // Spreadsheet numbering, counting the header
+        let number = i + 2;
+        let record = record.map_err(|e| anyhow!("Row {}: {}", number, e))?;
+        let cell = |column: &str| columns.get(column).and_then(|&i| record.get(i)).unwrap_or_default();
+        if record.iter().all(|field| field.is_empty()) {
+            continue;
+        }
+
+        let row = parse_row(&cell).map_err(|e| anyhow!("Row {}: {}", number, e))?;
+        let index = rows.len();
+        if let Some(id) = &row.task.label {
+            if by_id.insert(id.clone(), index).is_some() {
+                return Err(anyhow!("Row {}: duplicate id '{}'", number, id));
+            }
+        }
+        match cell("parent") {
+            "" => roots.push(index),
+            // Parents come first, so the tree can't loop
+            parent => {
+                let parent = *by_id.get(parent)
+                    .filter(|&&parent| parent != index)
+                    .ok_or_else(|| anyhow!(
+                        "Row {}: unknown parent '{}' (a parent must come before its subtasks)",
+                        number,
+                        parent
+                    ))?;
+                rows[parent].subtasks.push(index);
+            },
+        }
+        rows.push(row);
+    }
+
+    // Ticks per block, so they line up with a pre-order walk once lowered
+    let mut blocks: Vec<(Block, Vec<bool>)> = Vec::new();
+    for root in roots {
+        let (block, completed) = block_named(&mut blocks, &rows[root].block);
+        block.add_task(build(root, &rows, completed));
+    }
+
+    let (blocks, completed): (Vec<_>, Vec<_>) = blocks.into_iter().unzip();
+    lower_blocks(blocks, completed.concat(), config)
+}
+
+/// A row's task with its subtasks attached, recording ticks in pre-order.
+/// Subtasks live in their parent's block whatever their own `block` says.
+fn build(index: usize, rows: &[Row], completed: &mut Vec<bool>) -> Task {
+    let row = &rows[index];
+    completed.push(row.completed);
+    let mut task = row.task.clone();
+    for &subtask in &row.subtasks {
+        task.add_subtask(build(subtask, rows, completed));
+    }
+    task
+}
+
+fn parse_row<'a>(cell: &impl Fn(&str) -> &'a str) -> Result<Row> {
+    let name = cell("name");
+    if name.is_empty() {
+        return Err(anyhow!("task has no name"));
+    }
+    let block = match cell("block") {
+        "" => "default",
+        block => block,
+    };
+
+    let duration = match cell("duration") {
+        "" => None,
+        duration => Some(
+            duration.parse::<u64>().ok().map(TaskDuration::from_minutes)
+                .or_else(|| parse_duration(duration))
+                .ok_or_else(|| anyhow!("invalid duration '{}'", duration))?,
+        ),
+    };
+    let priority = match cell("priority") {
+        "" => Priority::default(),
+        priority => Priority::from_str(priority).map_err(|_| anyhow!("invalid priority '{}'", priority))?,
+    };
+    let completed = match cell("completed").to_lowercase().as_str() {
+        "" | "false" | "no" | "0" => false,
+        "true" | "yes" | "x" | "1" => true,
+        other => return Err(anyhow!("invalid completed value '{}'", other)),
+    };
+    let list = |column: &str| -> Vec<String> {
+        cell(column).split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect()
+    };
+    let label = match cell("id") {
+        "" => None,
+        id => Some(id.to_string()),
+    };
+
+    let link = match cell("link") {
+        "" => None,
+        link => Some(link.to_string()),
+    };
+
+    let mut task = Task::new(name.to_string())
+        .with_params(list("params"))
+        .with_duration(duration)
+        .with_tags(list("tags").into_iter().collect())
+        .with_priority(priority)
+        .with_dependencies(list("deps"))
+        .with_link(link)
+        .with_label(label);
+    for note in cell("notes").split("\n\n").map(str::trim).filter(|n| !n.is_empty()) {
+        task.add_note(note.to_string());
+    }
+
+    Ok(Row { task, block: block.to_string(), completed, subtasks: Vec::new() })
+}
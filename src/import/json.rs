@@ -0,0 +1,84 @@
+//! Plans as JSON, as written by `--output-format json`.
+//!
+//! Reads the current schema (see `codegen::json`), version 2, which is the
+//! same layout without `due` and `run`, and version 1, the raw IR layout older
+//! releases wrote. Either way `depends_on` may refer to
+//! tasks by name, like `after:`, and the result is checked for duplicate IDs.
+
+use crate::codegen::json::{JsonPlan, JSON_SCHEMA_VERSION};
+use crate::config::Config;
+use crate::ir::{self, IRBlock, IRMetadata, IRProgram, IRTask};
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// Version 1: `IRProgram` as serde wrote it, `task_map` and all
+#[derive(Deserialize)]
+struct LegacyPlan {
+    blocks: Vec<IRBlock>,
+    metadata: Option<IRMetadata>,
+}
+
+pub fn load_json(input: &str, config: &Config) -> Result<IRProgram> {
+    let value: serde_json::Value = serde_json::from_str(input)
+        .map_err(|e| anyhow!("Invalid JSON: {}", e))?;
+
+    // Check the version before the layout, so an old or new file gets a
+    // useful error instead of a confusing missing-field one
+    let version = match value.get("schema_version").map(|v| v.as_u64()) {
+        None => return Err(anyhow!("Missing schema_version (expected {})", JSON_SCHEMA_VERSION)),
+        Some(Some(version)) => version,
+        Some(None) => return Err(anyhow!("schema_version must be a number")),
+    };
+
+    let (mut blocks, metadata) = match version {
+        1 => {
+            let plan: LegacyPlan = serde_json::from_value(value)
+                .map_err(|e| anyhow!("Invalid plan: {}", e))?;
+            (plan.blocks, plan.metadata)
+        },
+        2 | 3 => {
+            let plan: JsonPlan = serde_json::from_value(value)
+                .map_err(|e| anyhow!("Invalid plan: {}", e))?;
+            let blocks = plan.blocks.iter().map(|b| b.to_ir()).collect::<Result<Vec<_>>>()?;
+            let metadata = plan.metadata.map(|m| m.to_ir()).transpose()?;
+            (blocks, metadata)
+        },
+        _ => {
+            return Err(anyhow!(
+                "Unsupported schema_version {} (this version of zibox reads 1 to {})",
+                version,
+                JSON_SCHEMA_VERSION
+            ));
+        },
+    };
+
+    let mut ids = HashSet::new();
+    for block in &mut blocks {
+        for task in &mut block.tasks {
+            check_task(task, &block.name, &mut ids)?;
+        }
+    }
+    ir::resolve_dependencies(&mut blocks);
+
+    let metadata = match metadata {
+        Some(metadata) => metadata,
+        None => config.to_ir_metadata()?,
+    };
+    Ok(IRProgram::new(blocks, metadata))
+}
+
+/// Reject missing or duplicate IDs, and put every task in its enclosing block
+fn check_task(task: &mut IRTask, block: &str, ids: &mut HashSet<String>) -> Result<()> {
+    if task.id.is_empty() {
+        return Err(anyhow!("Task '{}' has an empty id", task.name));
+    }
+    if !ids.insert(task.id.clone()) {
+        return Err(anyhow!("Duplicate task id: {}", task.id));
+    }
+    task.block = block.to_string();
+    for subtask in &mut task.subtasks {
+        check_task(subtask, block, ids)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//! Markdown checklists: headings become blocks and `- [ ]` items tasks.
+//!
+//! An item's text may carry `#tags`, a priority like `!high` and a trailing
+//! duration like `(30m)` or `(1h30m)`. Items nested under an item are its
+//! subtasks; plain bullets nested under it are its notes, or its link when
+//! they hold nothing but a URL. Other lines are ignored.
+
+use super::{is_url, lower_blocks, parse_duration, task_name};
+use crate::ast::{Block, Priority, Task};
+use crate::config::Config;
+use crate::ir::IRProgram;
+use crate::utils::slugify;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+pub fn load_markdown(input: &str, config: &Config) -> Result<IRProgram> {
+    let mut blocks = Vec::new();
+    let mut block = Block::new("default".to_string());
+    // Open items by indent, like the parser's task tree
+    let mut open: Vec<(usize, Task)> = Vec::new();
+    // Whether each item was ticked, in document order
+    let mut completed = Vec::new();
+
+    for line in input.lines() {
+        if let Some(heading) = heading(line) {
+            close_to(&mut open, 0, &mut block);
+            if !block.tasks.is_empty() {
+                blocks.push(block);
+            }
+            block = Block::new(slugify(heading));
+            continue;
+        }
+
+        let Some((indent, checked, text)) = list_item(line) else {
+            continue;
+        };
+        match checked {
+            Some(checked) => {
+                close_to(&mut open, indent, &mut block);
+                open.push((indent, parse_item(text)));
+                completed.push(checked);
+            },
+            None => {
+                // A plain bullet belongs to the closest item it's nested under
+                if let Some((_, task)) = open.iter_mut().rev().find(|(open_indent, _)| *open_indent < indent) {
+                    match as_link(text) {
+                        Some(url) if task.link.is_none() => task.link = Some(url.to_string()),
+                        _ => task.add_note(text.to_string()),
+                    }
+                }
+            },
+        }
+    }
+    close_to(&mut open, 0, &mut block);
+    if !block.tasks.is_empty() {
+        blocks.push(block);
+    }
+
+    lower_blocks(blocks, completed, config)
+}
+
+/// Close every open item indented at least as deep as `indent`
+fn close_to(open: &mut Vec<(usize, Task)>, indent: usize, block: &mut Block) {
+    while open.last().is_some_and(|(open_indent, _)| *open_indent >= indent) {
+        let (_, task) = open.pop().unwrap();
+        match open.last_mut() {
+            Some((_, parent)) => parent.add_subtask(task),
+            None => block.add_task(task),
+        }
+    }
+}
+
+/// The text of an ATX heading (`## Morning`)
+fn heading(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim().trim_end_matches('#').trim())
+}
+
+/// A list item's indent, checkbox state (`None` for a plain bullet) and text
+fn list_item(line: &str) -> Option<(usize, Option<bool>, &str)> {
+    let indent = line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    let trimmed = line.trim_start();
+
+    // `-`, `*`, `+` or an ordered `1.` / `1)` marker, then a space
+    let rest = if let Some(rest) = trimmed.strip_prefix(['-', '*', '+']) {
+        rest
+    } else {
+        let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        trimmed[digits..].strip_prefix(['.', ')'])?
+    };
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = rest.trim_start();
+
+    for (checkbox, checked) in [("[ ]", false), ("[x]", true), ("[X]", true)] {
+        if let Some(text) = rest.strip_prefix(checkbox) {
+            return Some((indent, Some(checked), text.trim()));
+        }
+    }
+    Some((indent, None, rest.trim_end()))
+}
+
+fn parse_item(text: &str) -> Task {
+    let mut words = Vec::new();
+    let mut tags = HashSet::new();
+    let mut priority = Priority::default();
+
+    for word in text.split_whitespace() {
+        if let Some(tag) = word.strip_prefix('#').filter(|t| !t.is_empty()) {
+            tags.insert(tag.to_string());
+        } else if let Some(p) = word.strip_prefix('!').and_then(|p| Priority::from_str(p).ok()) {
+            priority = p;
+        } else {
+            words.push(word);
+        }
+    }
+
+    let duration = words.last()
+        .and_then(|word| word.strip_prefix('(')?.strip_suffix(')'))
+        .and_then(parse_duration);
+    if duration.is_some() {
+        words.pop();
+    }
+    let (name, params) = task_name(&words);
+
+    Task::new(name)
+        .with_params(params)
+        .with_duration(duration)
+        .with_tags(tags)
+        .with_priority(priority)
+}
+
+/// A bullet that is just a URL, bare, in `<angle brackets>` or after a 🔗
+fn as_link(text: &str) -> Option<&str> {
+    let url = text.trim_start_matches('🔗').trim();
+    let url = url.strip_prefix('<').and_then(|u| u.strip_suffix('>')).unwrap_or(url);
+    is_url(url).then_some(url)
+}
@@ -0,0 +1,196 @@
+//! Taskwarrior's `task export`, as a JSON array or one task per line.
+//!
+//! Projects become blocks (`default` without one), `H`/`M`/`L` priorities map
+//! to high, medium and low (critical with `zibox_priority`), the `estimate`
+//! UDA is the duration and `due` the due date. Tasks keep their UUIDs as IDs
+//! and `depends` as dependencies; one with a `zibox_parent` UDA goes back
+//! under its parent. Annotations are notes, except a lone URL, which is the
+//! task's link. Deleted tasks and recurring templates are skipped, and
+//! `scheduled` is left to the scheduler.
+
+use super::{block_named, is_url, lower_blocks, parse_duration, task_name};
+use crate::ast::{Block, Priority, Task, TaskDuration};
+use crate::codegen::taskwarrior::{parse_timestamp, TaskwarriorTask};
+use crate::config::Config;
+use crate::ir::IRProgram;
+use anyhow::{Result, anyhow};
+use chrono::Local;
+use std::collections::{HashMap, HashSet};
+
+pub fn load_taskwarrior(input: &str, config: &Config) -> Result<IRProgram> {
+    let exported: Vec<TaskwarriorTask> = if input.trim_start().starts_with('[') {
+        serde_json::from_str(input).map_err(|e| anyhow!("Invalid Taskwarrior export: {}", e))?
+    } else {
+        // `rc.json.array=off` writes one object per line
+        serde_json::Deserializer::from_str(input)
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .map_err(|e| anyhow!("Invalid Taskwarrior export: {}", e))?
+    };
+
+    let exported: Vec<TaskwarriorTask> = exported.into_iter()
+        .filter(|task| !matches!(task.status.as_str(), "deleted" | "recurring"))
+        .collect();
+    // Dependencies on tasks that weren't exported (or were skipped) are dropped
+    let uuids: HashSet<&str> = exported.iter()
+        .map(|task| task.uuid.as_str())
+        .filter(|uuid| !uuid.is_empty())
+        .collect();
+
+    let mut by_uuid = HashMap::new();
+    for (index, task) in exported.iter().enumerate() {
+        if !task.uuid.is_empty() && by_uuid.insert(task.uuid.as_str(), index).is_some() {
+            return Err(anyhow!("Duplicate task uuid: {}", task.uuid));
+        }
+    }
+    let parents = parents(&exported, &by_uuid);
+
+    let mut tasks = Vec::new();
+    for (index, task) in exported.iter().enumerate() {
+        // A parent waits for its subtasks already
+        let subtasks: HashSet<&str> = exported.iter().zip(&parents)
+            .filter(|(_, parent)| **parent == Some(index))
+            .map(|(subtask, _)| subtask.uuid.as_str())
+            .collect();
+        tasks.push(to_task(task, &uuids, &subtasks)?);
+    }
+
+    let mut blocks: Vec<(Block, Vec<bool>)> = Vec::new();
+    for (index, task) in exported.iter().enumerate() {
+        if parents[index].is_some() {
+            continue;
+        }
+        let project = task.project.as_deref().filter(|p| !p.is_empty()).unwrap_or("default");
+        let (block, completed) = block_named(&mut blocks, project);
+        block.add_task(build(index, &exported, &tasks, &parents, completed));
+    }
+
+    let (blocks, completed): (Vec<_>, Vec<_>) = blocks.into_iter().unzip();
+    lower_blocks(blocks, completed.concat(), config)
+}
+
+/// Each task's parent, from `zibox_parent`. A parent that wasn't exported is
+/// ignored, and so is one that would make a loop.
+fn parents(exported: &[TaskwarriorTask], by_uuid: &HashMap<&str, usize>) -> Vec<Option<usize>> {
+    let mut parents: Vec<Option<usize>> = exported.iter()
+        .map(|task| task.zibox_parent.as_deref().and_then(|uuid| by_uuid.get(uuid).copied()))
+        .collect();
+    for index in 0..parents.len() {
+        let mut ancestor = parents[index];
+        for _ in 0..parents.len() {
+            match ancestor {
+                Some(a) if a == index => {
+                    parents[index] = None;
+                    break;
+                },
+                Some(a) => ancestor = parents[a],
+                None => break,
+            }
+        }
+    }
+    parents
+}
+
+/// A task with its subtasks attached, recording ticks in pre-order.
+/// Subtasks live in their parent's project whatever their own.
+fn build(
+    index: usize,
+    exported: &[TaskwarriorTask],
+    tasks: &[Task],
+    parents: &[Option<usize>],
+    completed: &mut Vec<bool>,
+) -> Task {
+    completed.push(exported[index].status == "completed");
+    let mut task = tasks[index].clone();
+    for subtask in (0..tasks.len()).filter(|&s| parents[s] == Some(index)) {
+        task.add_subtask(build(subtask, exported, tasks, parents, completed));
+    }
+    task
+}
+
+fn to_task(exported: &TaskwarriorTask, uuids: &HashSet<&str>, subtasks: &HashSet<&str>) -> Result<Task> {
+    let words: Vec<&str> = exported.description.split_whitespace().collect();
+    if words.is_empty() {
+        return Err(anyhow!("Task {} has no description", exported.uuid));
+    }
+    let (name, params) = task_name(&words);
+
+    let priority = match exported.priority.as_deref() {
+        _ if exported.zibox_priority.as_deref() == Some("critical") => Priority::Critical,
+        Some("H") => Priority::High,
+        Some("L") => Priority::Low,
+        _ => Priority::Medium,
+    };
+    let duration = exported.estimate.as_deref()
+        .map(|estimate| parse_estimate(estimate)
+            .ok_or_else(|| anyhow!("Task '{}' has an invalid estimate '{}'", exported.description, estimate)))
+        .transpose()?;
+    let due = exported.due.as_deref()
+        .map(|due| parse_timestamp(due)
+            .map(|due| due.with_timezone(&Local).date_naive())
+            .ok_or_else(|| anyhow!("Task '{}' has an invalid due date '{}'", exported.description, due)))
+        .transpose()?;
+    let depends_on = exported.depends.iter()
+        .filter(|uuid| uuids.contains(uuid.as_str()) && !subtasks.contains(uuid.as_str()))
+        .cloned()
+        .collect();
+
+    let mut link = None;
+    let mut notes = Vec::new();
+    for annotation in &exported.annotations {
+        let text = annotation.description.trim();
+        if link.is_none() && is_url(text) {
+            link = Some(text.to_string());
+        } else {
+            notes.push(annotation.description.clone());
+        }
+    }
+
+    let mut task = Task::new(name)
+        .with_params(params)
+        .with_duration(duration)
+        .with_tags(exported.tags.iter().cloned().collect())
+        .with_priority(priority)
+        .with_dependencies(depends_on)
+        .with_link(link)
+        .with_due(due)
+        .with_label((!exported.uuid.is_empty()).then(|| exported.uuid.clone()));
+    for note in notes {
+        task.add_note(note);
+    }
+    Ok(task)
+}
+
+/// An ISO 8601 duration like `PT1H30M` or `P1D`, seconds as Taskwarrior
+/// stores them, or a zibox literal like `90m`
+fn parse_estimate(estimate: &str) -> Option<TaskDuration> {
+    if let Ok(seconds) = estimate.parse::<u64>() {
+        return Some(TaskDuration::from_minutes(seconds.div_ceil(60)));
+    }
+    let Some(iso) = estimate.strip_prefix('P') else {
+        return parse_duration(estimate);
+    };
+
+    let mut seconds = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in iso.chars() {
+        match c {
+            'T' if !in_time && number.is_empty() => in_time = true,
+            '0'..='9' => number.push(c),
+            _ => {
+                let value: u64 = number.parse().ok()?;
+                number.clear();
+                seconds += value * match (c, in_time) {
+                    ('W', false) => 7 * 86400,
+                    ('D', false) => 86400,
+                    ('H', true) => 3600,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return None,
+                };
+            },
+        }
+    }
+    number.is_empty().then(|| TaskDuration::from_minutes(seconds.div_ceil(60)))
+}
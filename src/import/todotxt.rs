@@ -0,0 +1,205 @@
+//! todo.txt files, as written by `--output-format todotxt`.
+//!
+//! A line's first `@context` is its block and `+project`s are tags; any
+//! further contexts become tags too. `(A)` to `(D)` (or `pri:`) set the
+//! priority, `x` marks the task done, and `dur:`, `id:`, `after:`, `parent:`,
+//! `due:`, `link:` and percent-encoded `note:`s fill in the rest. Dates and
+//! other key-values, `t:` included, are accepted but ignored, since the
+//! scheduler decides when things happen.
+
+use super::{block_named, is_url, lower_blocks, parse_duration, task_name};
+use crate::ast::{Block, Priority, Task};
+use crate::config::Config;
+use crate::ir::IRProgram;
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+
+struct Item {
+    task: Task,
+    block: Option<String>,
+    completed: bool,
+    subtasks: Vec<usize>,
+}
+
+pub fn load_todotxt(input: &str, config: &Config) -> Result<IRProgram> {
+    let mut items: Vec<Item> = Vec::new();
+    let mut roots = Vec::new();
+    let mut by_id = HashMap::new();
+
+    for (number, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (item, parent) = parse_line(line).map_err(|e| anyhow!("Line {}: {}", number + 1, e))?;
+        let index = items.len();
+        if let Some(id) = &item.task.label {
+            if by_id.insert(id.clone(), index).is_some() {
+                return Err(anyhow!("Line {}: duplicate id '{}'", number + 1, id));
+            }
+        }
+        match parent {
+            // Parents come first, so the tree can't loop
+            Some(parent) => {
+                let parent = *by_id.get(&parent)
+                    .filter(|&&parent| parent != index)
+                    .ok_or_else(|| anyhow!(
+                        "Line {}: unknown parent '{}' (a parent must come before its subtasks)",
+                        number + 1,
+                        parent
+                    ))?;
+                items[parent].subtasks.push(index);
+            },
+            None => roots.push(index),
+        }
+        items.push(item);
+    }
+
+    // Ticks per block, so they line up with a pre-order walk once lowered
+    let mut blocks: Vec<(Block, Vec<bool>)> = Vec::new();
+    for root in roots {
+        let name = items[root].block.clone().unwrap_or_else(|| "default".to_string());
+        let (block, completed) = block_named(&mut blocks, &name);
+        block.add_task(build(root, &items, completed));
+    }
+
+    let (blocks, completed): (Vec<_>, Vec<_>) = blocks.into_iter().unzip();
+    lower_blocks(blocks, completed.concat(), config)
+}
+
+/// An item's task with its subtasks attached, recording ticks in pre-order.
+/// Subtasks live in their parent's block whatever their own `@context`.
+fn build(index: usize, items: &[Item], completed: &mut Vec<bool>) -> Task {
+    let item = &items[index];
+    completed.push(item.completed);
+    let mut task = item.task.clone();
+    for &subtask in &item.subtasks {
+        task.add_subtask(build(subtask, items, completed));
+    }
+    task
+}
+
+fn parse_line(line: &str) -> Result<(Item, Option<String>)> {
+    let mut words = line.split_whitespace().peekable();
+
+    let completed = words.next_if_eq(&"x").is_some();
+    // Completion and creation dates
+    while words.next_if(|word| NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok()).is_some() {}
+    let mut priority = words.next_if(|word| priority_from_cookie(word).is_some())
+        .and_then(priority_from_cookie);
+    while words.next_if(|word| NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok()).is_some() {}
+
+    let mut description = Vec::new();
+    let mut tags = HashSet::new();
+    let mut block = None;
+    let mut duration = None;
+    let mut label = None;
+    let mut parent = None;
+    let mut depends_on = Vec::new();
+    let mut link = None;
+    let mut due = None;
+    let mut notes = Vec::new();
+
+    for word in words {
+        if let Some(tag) = word.strip_prefix('+').filter(|t| !t.is_empty()) {
+            tags.insert(tag.to_string());
+        } else if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+            match block {
+                None => block = Some(context.to_string()),
+                Some(_) => {
+                    tags.insert(context.to_string());
+                },
+            }
+        } else if is_url(word) {
+            link.get_or_insert_with(|| word.to_string());
+        } else if let Some((key, value)) = key_value(word) {
+            match key {
+                "dur" => {
+                    duration = Some(parse_duration(value).ok_or_else(|| anyhow!("invalid duration '{}'", value))?);
+                },
+                "pri" => {
+                    priority = Some(priority_from_letter(value).ok_or_else(|| anyhow!("invalid priority '{}'", value))?);
+                },
+                "id" => label = Some(value.to_string()),
+                "parent" => parent = Some(value.to_string()),
+                "after" => depends_on.extend(value.split(',').filter(|d| !d.is_empty()).map(String::from)),
+                "link" => link = Some(value.to_string()),
+                "due" => {
+                    due = Some(NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                        .map_err(|_| anyhow!("invalid due date '{}'", value))?);
+                },
+                "note" => notes.push(decode(value)),
+                _ => {},
+            }
+        } else {
+            description.push(word);
+        }
+    }
+
+    if description.is_empty() {
+        return Err(anyhow!("task has no description"));
+    }
+    let (name, params) = task_name(&description);
+    let mut task = Task::new(name)
+        .with_params(params)
+        .with_duration(duration)
+        .with_tags(tags)
+        .with_priority(priority.unwrap_or_default())
+        .with_dependencies(depends_on)
+        .with_link(link)
+        .with_due(due)
+        .with_label(label);
+    for note in notes {
+        task.add_note(note);
+    }
+
+    Ok((Item { task, block, completed, subtasks: Vec::new() }, parent))
+}
+
+/// `key:value`, where the key is a word and neither side is empty
+fn key_value(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once(':')?;
+    let is_key = key.starts_with(|c: char| c.is_ascii_alphabetic())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    (is_key && !value.is_empty()).then_some((key, value))
+}
+
+/// Undo the percent-encoding of a `note:`; a `%` that doesn't start an
+/// escape is kept as it is
+fn decode(value: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail.get(..2)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(escaped) if byte == b'%' => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            },
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            },
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// `(A)` is critical and `(D)` low; todo.txt allows down to `(Z)`, which is
+/// low as well
+fn priority_from_cookie(word: &str) -> Option<Priority> {
+    priority_from_letter(word.strip_prefix('(')?.strip_suffix(')')?)
+}
+
+fn priority_from_letter(letter: &str) -> Option<Priority> {
+    match letter {
+        "A" => Some(Priority::Critical),
+        "B" => Some(Priority::High),
+        "C" => Some(Priority::Medium),
+        _ if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase()) => Some(Priority::Low),
+        _ => None,
+    }
+}
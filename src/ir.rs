@@ -1,5 +1,6 @@
 use crate::ast::{Block as ASTBlock, Task as ASTTask, TaskDuration, Priority};
-use chrono::{DateTime, Local, Duration, NaiveTime};
+use crate::utils::stable_hash;
+use chrono::{DateTime, Local, Duration, NaiveDate, NaiveTime};
 use serde::{Serialize, Deserialize};
 use std::collections::{HashMap, HashSet};
 
@@ -7,15 +8,37 @@ use std::collections::{HashMap, HashSet};
 pub struct IRTask {
     pub id: String,
     pub name: String,
+    // Everything below is optional in JSON input
+    #[serde(default)]
     pub params: Vec<String>,
+    #[serde(default)]
     pub duration: Option<TaskDuration>,
+    #[serde(default)]
     pub block: String,
+    #[serde(default)]
     pub tags: HashSet<String>,
+    #[serde(default)]
     pub priority: Priority,
+    #[serde(default)]
     pub depends_on: Vec<String>,
+    #[serde(default)]
     pub scheduled_start: Option<DateTime<Local>>,
+    #[serde(default)]
     pub scheduled_end: Option<DateTime<Local>>,
+    #[serde(default)]
     pub completed: bool,
+    #[serde(default)]
+    pub subtasks: Vec<IRTask>,
+    #[serde(default)]
+    pub notes: Vec<String>,
+    #[serde(default)]
+    pub link: Option<String>,
+    /// The day the task is due, as opposed to when it's scheduled
+    #[serde(default)]
+    pub due: Option<NaiveDate>,
+    /// Command to launch when the task starts
+    #[serde(default)]
+    pub run: Option<String>,
 }
 
 impl IRTask {
@@ -24,7 +47,7 @@ impl IRTask {
             id,
             name: ast_task.name.clone(),
             params: ast_task.params.clone(),
-            duration: ast_task.duration.clone(),
+            duration: ast_task.effective_duration(),
             block: block_name.to_string(),
             tags: ast_task.tags.clone(),
             priority: ast_task.priority.clone(),
@@ -32,6 +55,11 @@ impl IRTask {
             scheduled_start: None,
             scheduled_end: None,
             completed: false,
+            subtasks: Vec::new(),
+            notes: ast_task.notes.clone(),
+            link: ast_task.link.clone(),
+            due: ast_task.due,
+            run: ast_task.run.clone(),
         }
     }
     
@@ -46,10 +74,37 @@ impl IRTask {
         self
     }
     
+    /// Set the task's time slot and lay its subtasks out back to back within it
+    pub fn set_schedule(&mut self, start: DateTime<Local>, end: DateTime<Local>) {
+        // A task without a duration has no slot to fill, so it stays
+        // unscheduled instead of getting one that ends as it starts
+        if end <= start {
+            return;
+        }
+        self.scheduled_start = Some(start);
+        self.scheduled_end = Some(end);
+        
+        let mut current_time = start;
+        for subtask in &mut self.subtasks {
+            let subtask_end = (current_time + Duration::minutes(subtask.duration_minutes() as i64)).min(end);
+            subtask.set_schedule(current_time, subtask_end);
+            current_time = subtask_end;
+        }
+    }
+    
     pub fn has_tag(&self, tag: &str) -> bool {
         self.tags.contains(tag)
     }
     
+    /// This task followed by all of its subtasks, depth first
+    pub fn flatten(&self) -> Vec<&IRTask> {
+        let mut tasks = vec![self];
+        for subtask in &self.subtasks {
+            tasks.extend(subtask.flatten());
+        }
+        tasks
+    }
+    
     pub fn display_name(&self) -> String {
         if self.params.is_empty() {
             self.name.clone()
@@ -66,6 +121,7 @@ pub struct IRBlock {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct IRMetadata {
     pub timezone: String,
     pub workday_start: NaiveTime,
@@ -99,9 +155,9 @@ impl IRProgram {
     pub fn new(blocks: Vec<IRBlock>, metadata: IRMetadata) -> Self {
         let mut task_map = HashMap::new();
         
-        // Build a map of task IDs to tasks for quick lookup
+        // Build a map of task IDs to tasks (subtasks included) for quick lookup
         for block in &blocks {
-            for task in &block.tasks {
+            for task in block.tasks.iter().flat_map(|t| t.flatten()) {
                 task_map.insert(task.id.clone(), task.clone());
             }
         }
@@ -132,18 +188,88 @@ impl IRProgram {
 
 pub fn to_ir(ast_blocks: &[ASTBlock], metadata: IRMetadata) -> IRProgram {
     let mut blocks = vec![];
-    let mut task_id_counter = 0;
+    let mut occurrences = HashMap::new();
     
     for b in ast_blocks {
         let mut tasks = vec![];
         for t in &b.tasks {
-            let id = format!("task_{}", task_id_counter);
-            task_id_counter += 1;
-            
-            tasks.push(IRTask::new(id, t, &b.name));
+            tasks.push(lower_task(t, &b.name, &b.name, &mut occurrences));
         }
         blocks.push(IRBlock { name: b.name.clone(), tasks });
     }
     
+    resolve_dependencies(&mut blocks);
+    
     IRProgram::new(blocks, metadata)
 }
+
+fn lower_task(
+    ast_task: &ASTTask,
+    block_name: &str,
+    scope: &str,
+    occurrences: &mut HashMap<String, usize>,
+) -> IRTask {
+    let id = match &ast_task.label {
+        Some(label) => label.clone(),
+        None => {
+            // Derive the ID from the task's content so inserting or reordering
+            // lines elsewhere doesn't renumber it. Identical tasks in the same
+            // scope are told apart by how many came before them.
+            let key = format!("{}\u{0}{}\u{0}{}", scope, ast_task.name, ast_task.params.join(","));
+            let occurrence = occurrences.entry(key.clone()).or_insert(0);
+            let hash = stable_hash(&format!("{}\u{0}{}", key, occurrence));
+            *occurrence += 1;
+            format!("task_{:08x}", (hash ^ (hash >> 32)) as u32)
+        }
+    };
+    
+    let mut task = IRTask::new(id, ast_task, block_name);
+    task.subtasks = ast_task.subtasks.iter()
+        .map(|t| lower_task(t, block_name, &task.id, occurrences))
+        .collect();
+    task
+}
+
+/// Whether `id` is one derived by [`to_ir`] rather than an `id:` label
+pub fn is_generated_id(id: &str) -> bool {
+    id.strip_prefix("task_")
+        .is_some_and(|hash| hash.len() == 8 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Rewrite `after:` references to task IDs. A reference matches a task ID or
+/// label first, then a task name, then a display name like `write(report)`;
+/// anything unmatched is kept as written.
+pub(crate) fn resolve_dependencies(blocks: &mut [IRBlock]) {
+    let mut ids = HashSet::new();
+    let mut by_name = HashMap::new();
+    let mut by_display_name = HashMap::new();
+    
+    // The first task with a given name wins
+    for task in blocks.iter().flat_map(|b| b.tasks.iter()).flat_map(|t| t.flatten()) {
+        ids.insert(task.id.clone());
+        by_name.entry(task.name.clone()).or_insert_with(|| task.id.clone());
+        by_display_name.entry(task.display_name()).or_insert_with(|| task.id.clone());
+    }
+    
+    let lookup = |dep: &str| -> Option<String> {
+        if ids.contains(dep) {
+            return None;
+        }
+        by_name.get(dep).or_else(|| by_display_name.get(dep)).cloned()
+    };
+    
+    fn rewrite(task: &mut IRTask, lookup: &dyn Fn(&str) -> Option<String>) {
+        for dep in &mut task.depends_on {
+            if let Some(id) = lookup(dep) {
+                *dep = id;
+            }
+        }
+        for subtask in &mut task.subtasks {
+            rewrite(subtask, lookup);
+        }
+    }
+    
+    for task in blocks.iter_mut().flat_map(|b| b.tasks.iter_mut()) {
+        rewrite(task, &lookup);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::syntax::{SyntaxTree, NodeKind, Attribute};
 use thiserror::Error;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -8,7 +9,14 @@ pub enum Token {
     Tag(String),
     Priority(String),
     DependsOn(Vec<String>),
-    Indent,
+    Link(String),
+    Due(String),
+    Run(String),
+    Label(String),
+    Note(String),
+    Let(String, String),
+    Set(String, String),
+    Indent(usize),
     Newline,
     EOF,
 }
@@ -22,52 +30,42 @@ pub enum LexerError {
 pub type LexerResult<T> = Result<T, LexerError>;
 
 pub fn tokenize(input: &str) -> Vec<Token> {
+    let tree = SyntaxTree::parse(input);
     let mut tokens = Vec::new();
-    for line in input.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            tokens.push(Token::Newline);
-            continue;
-        }
-        
-        // Block definition
-        if trimmed.starts_with("@") {
-            tokens.push(Token::Block(trimmed[1..].to_string()));
-            tokens.push(Token::Newline);
-            continue;
-        }
-        
-        // Task with possible attributes
-        let mut task_parts = trimmed.split_whitespace().collect::<Vec<_>>();
-        let mut task_name = String::new();
-        
-        // Extract the task name (which might include parameters)
-        if !task_parts.is_empty() {
-            task_name = task_parts[0].to_string();
-            task_parts.remove(0);
-        }
-        
-        tokens.push(Token::Task(task_name));
-        
-        // Process remaining parts (duration, tags, priority, dependencies)
-        for part in task_parts {
-            if part.starts_with("[") && part.ends_with("]") {
-                // Duration
-                tokens.push(Token::Duration(part.trim_matches(['[', ']']).to_string()));
-            } else if part.starts_with("#") {
-                // Tag
-                tokens.push(Token::Tag(part[1..].to_string()));
-            } else if part.starts_with("p:") {
-                // Priority
-                tokens.push(Token::Priority(part[2..].to_string()));
-            } else if part.starts_with("after:") {
-                // Dependencies
-                let deps = part[6..].split(',').map(|s| s.to_string()).collect();
-                tokens.push(Token::DependsOn(deps));
-            }
+    
+    for node in &tree.nodes {
+        match &node.kind {
+            // Comments only matter to tooling that works on the syntax tree
+            NodeKind::Blank | NodeKind::Comment(_) => {},
+            NodeKind::Block(name) => tokens.push(Token::Block(name.clone())),
+            NodeKind::Let(name, value) => tokens.push(Token::Let(name.clone(), value.clone())),
+            NodeKind::Set(key, value) => tokens.push(Token::Set(key.clone(), value.clone())),
+            NodeKind::Note { text, .. } => tokens.push(Token::Note(text.clone())),
+            NodeKind::Task { name, attributes } => {
+                // Indentation decides which task a line nests under
+                if node.indent > 0 {
+                    tokens.push(Token::Indent(node.indent));
+                }
+                tokens.push(Token::Task(name.clone()));
+                
+                for attribute in attributes {
+                    match attribute {
+                        Attribute::Duration(d) => tokens.push(Token::Duration(d.clone())),
+                        Attribute::Tag(t) => tokens.push(Token::Tag(t.clone())),
+                        Attribute::Priority(p) => tokens.push(Token::Priority(p.clone())),
+                        Attribute::DependsOn(deps) => tokens.push(Token::DependsOn(deps.clone())),
+                        Attribute::Label(l) => tokens.push(Token::Label(l.clone())),
+                        Attribute::Link(l) => tokens.push(Token::Link(l.clone())),
+                        Attribute::Due(d) => tokens.push(Token::Due(d.clone())),
+                        Attribute::Run(r) => tokens.push(Token::Run(r.clone())),
+                        Attribute::Unknown(_) => {},
+                    }
+                }
+            },
         }
         
-        tokens.push(Token::Newline);
+        // One Newline per source line, so token positions map back to lines
+        tokens.extend(std::iter::repeat_n(Token::Newline, node.line_count()));
     }
     
     tokens.push(Token::EOF);
@@ -1,4 +1,10 @@
+pub mod actions;
 pub mod lexer;
+pub mod syntax;
+pub mod formatter;
+pub mod lint;
+pub mod lsp;
+pub mod import;
 pub mod parser;
 pub mod ast;
 pub mod ir;
@@ -15,30 +21,52 @@ use std::fs;
 
 /// Parse a .zbx file and return the AST
 pub fn parse_file(path: &Path) -> Result<Vec<ast::Block>> {
+    Ok(parse_program_file(path)?.blocks)
+}
+
+/// Parse a .zbx file into its blocks and file-level settings
+pub fn parse_program_file(path: &Path) -> Result<ast::Program> {
     let input = fs::read_to_string(path)
         .map_err(|e| anyhow!("Failed to read file {}: {}", path.display(), e))?;
     
     let tokens = lexer::tokenize(&input);
-    let ast = parser::parse(&tokens)
-        .map_err(|e| anyhow!("Failed to parse file {}: {:?}", path.display(), e))?;
+    let program = parser::parse_located(&tokens)
+        .map_err(|e| anyhow!("Failed to parse file {}: {}", path.display(), e))?;
     
-    Ok(ast)
+    Ok(program)
 }
 
-/// Compile a .zbx file to the specified output format
-pub fn compile(
-    input_path: &Path,
-    config: &config::Config,
-) -> Result<String> {
-    // Parse the input file
-    let ast = parse_file(input_path)?;
+/// Lower a .zbx file (or a plan in another input format) to IR without
+/// optimizing or scheduling it.
+///
+/// Returns the effective configuration, i.e. `config` with the file's `set`
+/// directives applied.
+pub fn lower_file(input_path: &Path, config: &config::Config) -> Result<(ir::IRProgram, config::Config)> {
+    let format = config.get_input_format(input_path)?;
+    if let import::InputFormat::Zbx = format {
+        let program = parse_program_file(input_path)?;
+        return lower_program(&program, config);
+    }
     
-    // Convert to IR
-    let metadata = config.to_ir_metadata()?;
-    let mut ir_program = ir::to_ir(&ast, metadata);
+    let input = fs::read_to_string(input_path)
+        .map_err(|e| anyhow!("Failed to read file {}: {}", input_path.display(), e))?;
+    import::import(&input, format, config)
+        .map_err(|e| anyhow!("Failed to import file {}: {}", input_path.display(), e))
+}
+
+/// Lower a parsed program to IR, applying its `set` directives to `config`
+pub fn lower_program(program: &ast::Program, config: &config::Config) -> Result<(ir::IRProgram, config::Config)> {
+    let mut config = config.clone();
+    config.apply_settings(&program.settings)?;
     
+    let metadata = config.to_ir_metadata()?;
+    Ok((ir::to_ir(&program.blocks, metadata), config))
+}
+
+/// Optimize and schedule lowered IR in place
+pub fn schedule_program(ir_program: &mut ir::IRProgram, config: &config::Config) -> Result<()> {
     // Optimize
-    optimizer::optimize(&mut ir_program);
+    optimizer::optimize(ir_program);
     
     // Schedule
     let schedule_mode = config.get_schedule_mode()?;
@@ -46,7 +74,24 @@ pub fn compile(
         schedule_mode,
         if config.deepwork_tag.is_empty() { None } else { Some(config.deepwork_tag.clone()) }
     );
-    scheduler.schedule(&mut ir_program);
+    scheduler.schedule(ir_program);
+    
+    Ok(())
+}
+
+/// Load, optimize and schedule a .zbx file or imported plan
+pub fn build_program(input_path: &Path, config: &config::Config) -> Result<ir::IRProgram> {
+    let (mut ir_program, config) = lower_file(input_path, config)?;
+    schedule_program(&mut ir_program, &config)?;
+    Ok(ir_program)
+}
+
+/// Compile a .zbx file to the specified output format
+pub fn compile(
+    input_path: &Path,
+    config: &config::Config,
+) -> Result<String> {
+    let ir_program = build_program(input_path, config)?;
     
     // Generate output
     let output_format = config.get_output_format()?;
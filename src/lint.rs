@@ -0,0 +1,346 @@
+//! Lint rules for .zbx plans, run by `zibox check`.
+//!
+//! Each rule has a stable ID and a name; either can be used in the `[lint]`
+//! table of `.ziboxrc` to change its severity or turn it off.
+
+use crate::ast::Priority;
+use crate::config::Config;
+use crate::ir::{self, IRProgram, IRTask};
+use crate::scheduler::ScheduleMode;
+use crate::syntax::{SyntaxTree, NodeKind, Attribute};
+use crate::utils::format_duration;
+use crate::lexer::{self, Token};
+use crate::parser;
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// More critical tasks than this and nothing is critical any more
+const MAX_CRITICAL_TASKS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Parse a configured severity; `None` turns the rule off
+    pub fn from_config(s: &str) -> Result<Option<Self>> {
+        match s.to_lowercase().as_str() {
+            "off" | "allow" => Ok(None),
+            "warn" | "warning" => Ok(Some(Severity::Warning)),
+            "error" | "deny" => Ok(Some(Severity::Error)),
+            _ => Err(anyhow!("Invalid lint severity: {}", s)),
+        }
+    }
+}
+
+pub struct Rule {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+}
+
+pub const SYNTAX_ERROR: Rule = Rule {
+    id: "ZB000",
+    name: "syntax-error",
+    severity: Severity::Error,
+    description: "The file doesn't parse, or a `set` directive is invalid",
+};
+
+pub const MISSING_DURATION: Rule = Rule {
+    id: "ZB001",
+    name: "missing-duration",
+    severity: Severity::Warning,
+    description: "Task has no duration and none can be derived from subtasks",
+};
+
+pub const OVER_CAPACITY: Rule = Rule {
+    id: "ZB002",
+    name: "over-capacity",
+    severity: Severity::Warning,
+    description: "Total planned duration is longer than the workday",
+};
+
+pub const DUPLICATE_TASK: Rule = Rule {
+    id: "ZB003",
+    name: "duplicate-task",
+    severity: Severity::Warning,
+    description: "The same task appears twice at the same level",
+};
+
+pub const UNKNOWN_PRIORITY: Rule = Rule {
+    id: "ZB004",
+    name: "unknown-priority",
+    severity: Severity::Error,
+    description: "Priority is not low, medium, high or critical",
+};
+
+pub const FORWARD_DEPENDENCY: Rule = Rule {
+    id: "ZB005",
+    name: "forward-dependency",
+    severity: Severity::Warning,
+    description: "Task depends on a task in a later block",
+};
+
+pub const UNUSED_TAG: Rule = Rule {
+    id: "ZB006",
+    name: "unused-tag",
+    severity: Severity::Warning,
+    description: "A focus or deepwork tag is configured but no task uses it",
+};
+
+pub const TOO_MANY_CRITICAL: Rule = Rule {
+    id: "ZB007",
+    name: "too-many-critical",
+    severity: Severity::Warning,
+    description: "More than three tasks are marked critical",
+};
+
+pub const RULES: &[&Rule] = &[
+    &SYNTAX_ERROR,
+    &MISSING_DURATION,
+    &OVER_CAPACITY,
+    &DUPLICATE_TASK,
+    &UNKNOWN_PRIORITY,
+    &FORWARD_DEPENDENCY,
+    &UNUSED_TAG,
+    &TOO_MANY_CRITICAL,
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub name: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// 1-based source line, when the problem has one
+    pub line: Option<usize>,
+    /// ID of the task the problem is about
+    pub task: Option<String>,
+}
+
+struct Linter {
+    severities: HashMap<&'static str, Option<Severity>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn new(config: &Config) -> Result<Self> {
+        let mut severities = HashMap::new();
+        for (key, value) in &config.lint {
+            let rule = RULES.iter()
+                .find(|r| r.id.eq_ignore_ascii_case(key) || r.name == key.as_str())
+                .ok_or_else(|| anyhow!("Unknown lint rule: {}", key))?;
+            severities.insert(rule.id, Severity::from_config(value)?);
+        }
+        Ok(Self { severities, diagnostics: Vec::new() })
+    }
+
+    fn severity(&self, rule: &Rule) -> Option<Severity> {
+        match self.severities.get(rule.id) {
+            Some(configured) => *configured,
+            None => Some(rule.severity),
+        }
+    }
+
+    fn report(&mut self, rule: &Rule, message: String, line: Option<usize>, task: Option<&IRTask>) {
+        if let Some(severity) = self.severity(rule) {
+            self.diagnostics.push(Diagnostic {
+                rule: rule.id,
+                name: rule.name,
+                severity,
+                message,
+                line,
+                task: task.map(|t| t.id.clone()),
+            });
+        }
+    }
+}
+
+/// Run every enabled rule over a .zbx source file
+pub fn check_source(source: &str, config: &Config) -> Result<Vec<Diagnostic>> {
+    let mut linter = Linter::new(config)?;
+    let tree = SyntaxTree::parse(source);
+
+    // Priorities are checked on the syntax tree, since the parser rejects
+    // unknown ones outright. The compiler fails on them either way, so with
+    // the rule off they're still reported as syntax errors.
+    let priority_rule = if linter.severity(&UNKNOWN_PRIORITY).is_some() { &UNKNOWN_PRIORITY } else { &SYNTAX_ERROR };
+    for node in &tree.nodes {
+        if let NodeKind::Task { name, attributes } = &node.kind {
+            for attribute in attributes {
+                if let Attribute::Priority(p) = attribute {
+                    if Priority::from_str(p).is_err() {
+                        linter.report(
+                            priority_rule,
+                            format!("Task '{}' has unknown priority '{}'", name, p),
+                            Some(node.line + 1),
+                            None,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // With those reported, the rest of the plan is linted as if they weren't
+    // there, so one bad priority doesn't hide every other problem
+    let tokens: Vec<Token> = lexer::tokenize(source).into_iter()
+        .filter(|t| !matches!(t, Token::Priority(p) if Priority::from_str(p).is_err()))
+        .collect();
+    let program = match parser::parse_located(&tokens) {
+        Ok(program) => program,
+        Err(e) => {
+            linter.report(&SYNTAX_ERROR, e.error.to_string(), Some(e.line), None);
+            return Ok(finish(linter));
+        }
+    };
+
+    let mut config = config.clone();
+    if let Err(e) = config.apply_settings(&program.settings) {
+        linter.report(&SYNTAX_ERROR, e.to_string(), None, None);
+    }
+    let ir_program = ir::to_ir(&program.blocks, config.to_ir_metadata()?);
+
+    let all_tasks: Vec<&IRTask> = ir_program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .collect();
+    let lines = task_lines(&tree, &ir_program);
+    let line_of = |task: &IRTask| lines.get(task.id.as_str()).map(|line| line + 1);
+
+    // Tasks without durations
+    for task in &all_tasks {
+        if task.duration.is_none() {
+            linter.report(
+                &MISSING_DURATION,
+                format!("Task '{}' has no duration", task.display_name()),
+                line_of(task),
+                Some(task),
+            );
+        }
+    }
+
+    // Total duration against the workday
+    let workday = (ir_program.metadata.workday_end - ir_program.metadata.workday_start).num_minutes().max(0) as u64;
+    let total = ir_program.total_duration();
+    if total > workday {
+        linter.report(
+            &OVER_CAPACITY,
+            format!(
+                "Plan takes {} but the workday is only {}",
+                format_duration(total),
+                format_duration(workday)
+            ),
+            None,
+            None,
+        );
+    }
+
+    // Duplicate tasks: across all top-level tasks, then among siblings
+    fn find_duplicates<'a>(tasks: impl Iterator<Item = &'a IRTask>, found: &mut Vec<&'a IRTask>) {
+        let mut seen = HashSet::new();
+        for task in tasks {
+            if !seen.insert(task.display_name()) {
+                found.push(task);
+            }
+            find_duplicates(task.subtasks.iter(), found);
+        }
+    }
+    let mut duplicates = Vec::new();
+    find_duplicates(ir_program.blocks.iter().flat_map(|b| b.tasks.iter()), &mut duplicates);
+    for task in duplicates {
+        linter.report(
+            &DUPLICATE_TASK,
+            format!("Task '{}' appears more than once", task.display_name()),
+            line_of(task),
+            Some(task),
+        );
+    }
+
+    // Dependencies on tasks in later blocks
+    let block_of: HashMap<&str, usize> = ir_program.blocks.iter().enumerate()
+        .flat_map(|(i, b)| b.tasks.iter().flat_map(|t| t.flatten()).map(move |t| (t.id.as_str(), i)))
+        .collect();
+    for (i, block) in ir_program.blocks.iter().enumerate() {
+        for task in block.tasks.iter().flat_map(|t| t.flatten()) {
+            for dep in &task.depends_on {
+                if let Some(&dep_block) = block_of.get(dep.as_str()) {
+                    if dep_block > i {
+                        let dep_name = ir_program.get_task(dep).map_or(dep.clone(), |t| t.display_name());
+                        linter.report(
+                            &FORWARD_DEPENDENCY,
+                            format!(
+                                "Task '{}' in @{} depends on '{}' in the later block @{}",
+                                task.display_name(),
+                                block.name,
+                                dep_name,
+                                ir_program.blocks[dep_block].name
+                            ),
+                            line_of(task),
+                            Some(task),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Configured tags that nothing uses
+    let used_tags: HashSet<&str> = all_tasks.iter()
+        .flat_map(|t| t.tags.iter().map(|tag| tag.as_str()))
+        .collect();
+    let mut configured_tags: Vec<&str> = config.focus_tags.iter().map(|t| t.as_str()).collect();
+    if matches!(config.get_schedule_mode(), Ok(ScheduleMode::DeepworkFirst)) {
+        configured_tags.push(&config.deepwork_tag);
+    }
+    for tag in configured_tags {
+        if !used_tags.contains(tag) {
+            linter.report(&UNUSED_TAG, format!("Tag '#{}' is configured but no task uses it", tag), None, None);
+        }
+    }
+
+    // Critical overload
+    let critical: Vec<_> = all_tasks.iter().filter(|t| t.priority == Priority::Critical).collect();
+    if critical.len() > MAX_CRITICAL_TASKS {
+        let task = critical[MAX_CRITICAL_TASKS];
+        linter.report(
+            &TOO_MANY_CRITICAL,
+            format!("{} tasks are critical (at most {} recommended)", critical.len(), MAX_CRITICAL_TASKS),
+            line_of(task),
+            Some(task),
+        );
+    }
+
+    Ok(finish(linter))
+}
+
+/// Zero-based source line of each task in freshly lowered (not yet optimized)
+/// IR, keyed by task ID.
+///
+/// Lowering keeps source order, so the tasks line up with the tree's task lines.
+pub(crate) fn task_lines<'a>(tree: &SyntaxTree, program: &'a IRProgram) -> HashMap<&'a str, usize> {
+    let tasks: Vec<&IRTask> = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .collect();
+    let lines: Vec<usize> = tree.nodes.iter()
+        .filter(|n| matches!(n.kind, NodeKind::Task { .. }))
+        .map(|n| n.line)
+        .collect();
+    if lines.len() != tasks.len() {
+        return HashMap::new();
+    }
+    tasks.into_iter().map(|t| t.id.as_str()).zip(lines).collect()
+}
+
+fn finish(linter: Linter) -> Vec<Diagnostic> {
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|d| d.line.unwrap_or(usize::MAX));
+    diagnostics
+}
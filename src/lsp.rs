@@ -0,0 +1,433 @@
+//! Language server for .zbx files, run by `zibox lsp`.
+//!
+//! Speaks LSP over stdio and re-analyses a document in full on every change:
+//! diagnostics come from the linter, hovers from a fresh schedule and
+//! formatting from the formatter. Columns are counted in UTF-16 code units,
+//! as the protocol expects.
+
+use crate::config::Config;
+use crate::ir::{IRProgram, IRTask};
+use crate::lexer::extract_task_params;
+use crate::lint::{self, Severity};
+use crate::syntax::{SyntaxTree, SyntaxNode, NodeKind, Attribute};
+use crate::utils::format_duration;
+use crate::{formatter, lexer, parser};
+use anyhow::{Result, anyhow};
+use log::warn;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, Formatting, GotoDefinition, HoverRequest, Request as _};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionResponse, CompletionTextEdit, Diagnostic,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, NumberOrString, OneOf, Position, PublishDiagnosticsParams, Range,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Uri,
+};
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeSet, HashMap};
+
+const PRIORITIES: &[&str] = &["low", "medium", "high", "critical"];
+
+/// Serve LSP requests on stdin/stdout until the client shuts us down
+pub fn run(config: Config) -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(connection, config)?;
+
+    // The writer thread only finishes once the connection is gone
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Serve LSP requests on any connection until the client shuts us down
+pub fn serve(connection: Connection, config: Config) -> Result<()> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![":".to_string(), ",".to_string(), "#".to_string()]),
+            ..Default::default()
+        }),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)
+        .map_err(|e| anyhow!("LSP initialization failed: {}", e))?;
+
+    let mut server = Server { connection, config, documents: HashMap::new() };
+    server.main_loop()
+}
+
+struct Server {
+    connection: Connection,
+    config: Config,
+    documents: HashMap<Uri, String>,
+}
+
+impl Server {
+    fn main_loop(&mut self) -> Result<()> {
+        let receiver = self.connection.receiver.clone();
+        for message in &receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)
+                        .map_err(|e| anyhow!("LSP shutdown failed: {}", e))? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    self.connection.sender.send(Message::Response(response))?;
+                },
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {},
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, |server, params| {
+                let position = params.text_document_position_params;
+                let source = server.documents.get(&position.text_document.uri)?;
+                hover(source, position.position, &server.config)
+            }),
+            Completion::METHOD => self.respond::<Completion>(request, |server, params| {
+                let position = params.text_document_position;
+                let source = server.documents.get(&position.text_document.uri)?;
+                Some(CompletionResponse::Array(completions(source, position.position, &server.config)))
+            }),
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, |server, params| {
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let range = definition(server.documents.get(&uri)?, position.position)?;
+                Some(GotoDefinitionResponse::Scalar(Location::new(uri, range)))
+            }),
+            Formatting::METHOD => self.respond::<Formatting>(request, |server, params| {
+                let source = server.documents.get(&params.text_document.uri)?;
+                Some(formatting(source))
+            }),
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported method: {}", method),
+            ),
+        }
+    }
+
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: impl FnOnce(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, handler(self, params)),
+            Err(e) => Response::new_err(request.id, ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+
+    /// Only a broken connection ends the server; a notification that can't
+    /// be decoded is logged and dropped
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                if let Some(params) = params::<DidOpenTextDocumentParams>(notification) {
+                    let document = params.text_document;
+                    self.documents.insert(document.uri.clone(), document.text);
+                    self.publish(document.uri)?;
+                }
+            },
+            DidChangeTextDocument::METHOD => {
+                if let Some(params) = params::<DidChangeTextDocumentParams>(notification) {
+                    // Full sync: the last change holds the whole document
+                    if let Some(change) = params.content_changes.into_iter().last() {
+                        self.documents.insert(params.text_document.uri.clone(), change.text);
+                    }
+                    self.publish(params.text_document.uri)?;
+                }
+            },
+            DidCloseTextDocument::METHOD => {
+                if let Some(params) = params::<DidCloseTextDocumentParams>(notification) {
+                    self.documents.remove(&params.text_document.uri);
+                    self.send_diagnostics(params.text_document.uri, Vec::new())?;
+                }
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+
+    fn publish(&self, uri: Uri) -> Result<()> {
+        let diagnostics = match self.documents.get(&uri) {
+            Some(source) => diagnostics(source, &self.config),
+            None => Vec::new(),
+        };
+        self.send_diagnostics(uri, diagnostics)
+    }
+
+    fn send_diagnostics(&self, uri: Uri, diagnostics: Vec<Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams { uri, diagnostics, version: None };
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(Message::Notification(notification))?;
+        Ok(())
+    }
+}
+
+/// Notifications get no reply, so there's no one to tell about bad params
+/// but the log
+fn params<P: DeserializeOwned>(notification: Notification) -> Option<P> {
+    let method = notification.method;
+    serde_json::from_value(notification.params)
+        .map_err(|e| warn!("Ignoring malformed {} notification: {}", method, e))
+        .ok()
+}
+
+/// Lint findings for a document, each spanning the line it's about
+pub fn diagnostics(source: &str, config: &Config) -> Vec<Diagnostic> {
+    let findings = match lint::check_source(source, config) {
+        Ok(findings) => findings,
+        // A broken `[lint]` table in .ziboxrc
+        Err(e) => return vec![Diagnostic {
+            range: Range::default(),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("zibox".to_string()),
+            message: e.to_string(),
+            ..Default::default()
+        }],
+    };
+
+    findings.into_iter()
+        .map(|finding| Diagnostic {
+            range: finding.line.map_or_else(Range::default, |line| line_range(source, line as u32 - 1)),
+            severity: Some(match finding.severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+            }),
+            code: Some(NumberOrString::String(finding.rule.to_string())),
+            source: Some("zibox".to_string()),
+            message: finding.message,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Completions for the attribute being typed: task names and labels after
+/// `after:`, priorities after `p:` and tags after `#`
+pub fn completions(source: &str, position: Position, config: &Config) -> Vec<CompletionItem> {
+    let tree = SyntaxTree::parse(source);
+    if !node_at(&tree, position.line).is_some_and(|n| matches!(n.kind, NodeKind::Task { .. })) {
+        return Vec::new();
+    }
+    let Some(line) = source.lines().nth(position.line as usize) else {
+        return Vec::new();
+    };
+    let before = &line[..byte_offset(line, position.character)];
+    let word = &before[before.rfind(char::is_whitespace).map_or(0, |i| i + 1)..];
+
+    let (partial, candidates, kind) = if let Some(deps) = word.strip_prefix("after:") {
+        let partial = deps.rsplit(',').next().unwrap_or_default();
+        (partial, dependency_targets(&tree), CompletionItemKind::REFERENCE)
+    } else if let Some(partial) = word.strip_prefix("p:") {
+        let priorities = PRIORITIES.iter().map(|p| p.to_string()).collect();
+        (partial, priorities, CompletionItemKind::ENUM_MEMBER)
+    } else if let Some(partial) = word.strip_prefix('#') {
+        (partial, known_tags(&tree, config, position.line as usize), CompletionItemKind::CONSTANT)
+    } else {
+        return Vec::new();
+    };
+
+    // Replace just the part after the `:`, `,` or `#`
+    let start = Position::new(position.line, position.character - utf16_len(partial));
+    candidates.into_iter()
+        .filter(|candidate| candidate.starts_with(partial))
+        .map(|candidate| CompletionItem {
+            label: candidate.clone(),
+            kind: Some(kind),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(Range::new(start, position), candidate))),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// When a task is scheduled, plus the rest of what the compiler knows about it
+pub fn hover(source: &str, position: Position, config: &Config) -> Option<Hover> {
+    let tree = SyntaxTree::parse(source);
+    let node = node_at(&tree, position.line)?;
+    if !matches!(node.kind, NodeKind::Task { .. }) {
+        return None;
+    }
+
+    let (lowered, config) = lower(source, config)?;
+    let id = task_id_at(&tree, &lowered, node.line)?;
+    let mut scheduled = lowered.clone();
+    crate::schedule_program(&mut scheduled, &config).ok()?;
+    let task = find_task(&scheduled, &id)?;
+
+    let mut lines = vec![format!("**{}** in `@{}`", task.display_name(), task.block)];
+    match (task.scheduled_start, task.scheduled_end) {
+        (Some(start), Some(end)) => lines.push(format!(
+            "{} – {} ({})",
+            start.format("%H:%M"),
+            end.format("%H:%M"),
+            format_duration(task.duration_minutes())
+        )),
+        _ => lines.push("Not scheduled".to_string()),
+    }
+
+    let mut details = vec![format!("Priority: {}", format!("{:?}", task.priority).to_lowercase())];
+    if !task.tags.is_empty() {
+        let mut tags: Vec<_> = task.tags.iter().map(|t| format!("#{}", t)).collect();
+        tags.sort();
+        details.push(format!("Tags: {}", tags.join(" ")));
+    }
+    if !task.depends_on.is_empty() {
+        let deps: Vec<_> = task.depends_on.iter()
+            .map(|dep| find_task(&scheduled, dep).map_or(dep.clone(), |t| t.display_name()))
+            .collect();
+        details.push(format!("After: {}", deps.join(", ")));
+    }
+    lines.push(details.join(" · "));
+    lines.push(format!("`{}`", task.id));
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: lines.join("\n\n"),
+        }),
+        range: Some(line_range(source, node.line as u32)),
+    })
+}
+
+/// The line of the task an `after:` reference under the cursor points at
+pub fn definition(source: &str, position: Position) -> Option<Range> {
+    let tree = SyntaxTree::parse(source);
+    let node = node_at(&tree, position.line)?;
+    let line = source.lines().nth(position.line as usize)?;
+
+    // The whitespace-separated word under the cursor
+    let offset = byte_offset(line, position.character);
+    let start = line[..offset].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    let end = line[offset..].find(char::is_whitespace).map_or(line.len(), |i| offset + i);
+    if !line[start..end].starts_with("after:") {
+        return None;
+    }
+
+    // Which of the comma-separated references the cursor is on
+    let deps_start = start + "after:".len();
+    let index = line[deps_start..offset.max(deps_start)].matches(',').count();
+
+    // Let the compiler resolve it, so the answer always matches the build
+    let (lowered, _) = lower(source, &Config::default())?;
+    let id = task_id_at(&tree, &lowered, node.line)?;
+    let target = find_task(&lowered, &id)?.depends_on.get(index)?;
+    let target_line = *lint::task_lines(&tree, &lowered).get(target.as_str())?;
+
+    Some(line_range(source, target_line as u32))
+}
+
+/// Edits turning the document into its canonical layout
+pub fn formatting(source: &str) -> Vec<TextEdit> {
+    let formatted = formatter::format_source(source);
+    if formatted == source {
+        return Vec::new();
+    }
+    vec![TextEdit::new(Range::new(Position::new(0, 0), end_position(source)), formatted)]
+}
+
+/// Parse and lower a document, or `None` if it doesn't parse
+fn lower(source: &str, config: &Config) -> Option<(IRProgram, Config)> {
+    let program = parser::parse_located(&lexer::tokenize(source)).ok()?;
+    crate::lower_program(&program, config).ok()
+}
+
+fn task_id_at(tree: &SyntaxTree, lowered: &IRProgram, line: usize) -> Option<String> {
+    lint::task_lines(tree, lowered).into_iter()
+        .find(|&(_, task_line)| task_line == line)
+        .map(|(id, _)| id.to_string())
+}
+
+fn find_task<'a>(program: &'a IRProgram, id: &str) -> Option<&'a IRTask> {
+    program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .find(|t| t.id == id)
+}
+
+fn node_at(tree: &SyntaxTree, line: u32) -> Option<&SyntaxNode> {
+    let line = line as usize;
+    tree.nodes.iter().find(|n| n.line <= line && line < n.line + n.line_count())
+}
+
+/// Everything `after:` accepts: labels, task names and full names
+fn dependency_targets(tree: &SyntaxTree) -> BTreeSet<String> {
+    let mut targets = BTreeSet::new();
+    for node in &tree.nodes {
+        if let NodeKind::Task { name, attributes } = &node.kind {
+            let (base, params) = extract_task_params(name);
+            targets.insert(base);
+            // Names with several parameters can't be written without spaces
+            if params.len() == 1 {
+                targets.insert(name.clone());
+            }
+            for attribute in attributes {
+                if let Attribute::Label(label) = attribute {
+                    targets.insert(label.clone());
+                }
+            }
+        }
+    }
+    targets
+}
+
+/// Tags used in the document plus the ones the configuration cares about,
+/// leaving out the line being edited so a half-typed tag doesn't suggest itself
+fn known_tags(tree: &SyntaxTree, config: &Config, editing: usize) -> BTreeSet<String> {
+    let mut tags: BTreeSet<String> = config.focus_tags.iter().cloned().collect();
+    if !config.deepwork_tag.is_empty() {
+        tags.insert(config.deepwork_tag.clone());
+    }
+    for node in tree.nodes.iter().filter(|n| n.line != editing) {
+        if let NodeKind::Task { attributes, .. } = &node.kind {
+            for attribute in attributes {
+                if let Attribute::Tag(tag) = attribute {
+                    if !tag.is_empty() {
+                        tags.insert(tag.clone());
+                    }
+                }
+            }
+        }
+    }
+    tags
+}
+
+/// A line's range without its indentation
+fn line_range(source: &str, line: u32) -> Range {
+    let text = source.lines().nth(line as usize).unwrap_or_default();
+    let content = text.trim_start();
+    let start = utf16_len(&text[..text.len() - content.len()]);
+    Range::new(Position::new(line, start), Position::new(line, start + utf16_len(content.trim_end())))
+}
+
+fn end_position(source: &str) -> Position {
+    let line = source.matches('\n').count() as u32;
+    let last = source.rsplit('\n').next().unwrap_or_default();
+    Position::new(line, utf16_len(last))
+}
+
+fn utf16_len(s: &str) -> u32 {
+    s.encode_utf16().count() as u32
+}
+
+/// Byte offset of a UTF-16 column, clamped to the line
+fn byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= character {
+            return i;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line.len()
+}
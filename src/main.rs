@@ -1,14 +1,17 @@
-use clap::{Parser, Subcommand, Args};
+use clap::{Parser, Subcommand};
 use std::path::{Path, PathBuf};
 use std::fs;
 use anyhow::{Result, anyhow};
 use colored::Colorize;
-use log::{info, warn, error};
+use log::info;
 use env_logger::Env;
 
 use zero_inbox::{
     config::Config,
-    codegen::{OutputFormat, visualize_schedule},
+    codegen::{self, visualize_schedule, OutputFormat},
+    formatter,
+    lint::{self, Severity},
+    lsp,
     runtime,
     utils::{sanitize_output_name, ensure_dir_exists},
 };
@@ -33,7 +36,11 @@ struct Cli {
     #[arg(value_name = "FILE")]
     input_file: Option<PathBuf>,
     
-    /// Output format (shell, markdown, json, calendar)
+    /// Input format (zbx, json, markdown, todotxt, taskwarrior, csv, tsv); guessed from the extension by default
+    #[arg(long, value_name = "FORMAT")]
+    input_format: Option<String>,
+    
+    /// Output format (shell, posix, powershell, markdown, json, calendar, zbx, todotxt, taskwarrior, org, csv, tsv, html, mermaid, plantuml, dot, systemd, cron, template)
     #[arg(long, value_name = "FORMAT")]
     output_format: Option<String>,
     
@@ -81,6 +88,26 @@ struct Cli {
     #[arg(long)]
     visualize_schedule: bool,
     
+    /// Draw the schedule as a Gantt chart (.svg or .png)
+    #[arg(long, value_name = "FILE")]
+    chart: Option<PathBuf>,
+    
+    /// Annotate zbx output with scheduled times and completion
+    #[arg(long)]
+    annotate: bool,
+    
+    /// How calendar output writes times (utc, floating, zoned)
+    #[arg(long, value_name = "STYLE")]
+    calendar_time: Option<String>,
+    
+    /// Include unscheduled tasks in calendar output as to-dos
+    #[arg(long)]
+    calendar_todos: bool,
+    
+    /// Render the plan with a template file (sets the output format to template)
+    #[arg(long, value_name = "FILE")]
+    template: Option<PathBuf>,
+    
     /// Run the compiled plan
     #[arg(long)]
     run: bool,
@@ -97,6 +124,49 @@ enum Commands {
         #[arg(value_name = "NAME")]
         name: String,
     },
+    
+    /// Rewrite .zbx files in canonical layout
+    Fmt {
+        /// Files to format in place
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+        
+        /// Only check formatting; fail if any file would change
+        #[arg(long)]
+        check: bool,
+    },
+    
+    /// Convert a plan to another format without scheduling it
+    Convert {
+        /// File to convert (.zbx, .json or .md)
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        
+        /// Format to convert to (zbx, json, ...)
+        #[arg(long, value_name = "FORMAT")]
+        to: String,
+        
+        /// Write to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    
+    /// Run a language server for .zbx files over stdio
+    Lsp,
+    
+    /// Print the JSON Schema for --output-format json
+    Schema,
+    
+    /// Lint .zbx files without compiling them
+    Check {
+        /// Files to check
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+        
+        /// Report format (text, json)
+        #[arg(long, value_name = "FORMAT", default_value = "text")]
+        format: String,
+    },
 }
 
 fn main() -> Result<()> {
@@ -110,6 +180,9 @@ fn main() -> Result<()> {
     let mut config = Config::load()?;
     
     // Override config with CLI args. User is always right (even when they're wrong).
+    if let Some(format) = cli.input_format {
+        config.input_format = Some(format);
+    }
     if let Some(format) = cli.output_format {
         config.output_format = format;
     }
@@ -140,6 +213,19 @@ fn main() -> Result<()> {
     config.dry_run = cli.dry_run;
     config.show_ir = cli.show_ir;
     config.visualize_schedule = cli.visualize_schedule;
+    if cli.annotate {
+        config.annotate = true;
+    }
+    if let Some(time) = cli.calendar_time {
+        config.calendar.time = Some(time);
+    }
+    if cli.calendar_todos {
+        config.calendar.todos = true;
+    }
+    if let Some(template) = &cli.template {
+        config.template = Some(template.to_string_lossy().to_string());
+        config.output_format = "template".to_string();
+    }
     
     // Handle subcommands (formats, new). Because sometimes you want more than just "run".
     if let Some(cmd) = cli.command {
@@ -150,6 +236,22 @@ fn main() -> Result<()> {
             Commands::New { name } => {
                 return create_new_file(&name);
             },
+            Commands::Fmt { files, check } => {
+                return format_files(&files, check);
+            },
+            Commands::Convert { file, to, output } => {
+                return convert_file(&file, &to, output.as_deref(), &config);
+            },
+            Commands::Schema => {
+                println!("{}", serde_json::to_string_pretty(&codegen::json_schema())?);
+                return Ok(());
+            },
+            Commands::Lsp => {
+                return lsp::run(config);
+            },
+            Commands::Check { files, format } => {
+                return check_files(&files, &format, &config);
+            },
         }
     }
     
@@ -174,9 +276,7 @@ fn main() -> Result<()> {
     
     // Show the IR if requested. For the nerds who want to see the sausage get made.
     if config.show_ir {
-        let ast = zero_inbox::parse_file(&input_file)?;
-        let metadata = config.to_ir_metadata()?;
-        let ir = zero_inbox::ir::to_ir(&ast, metadata);
+        let (ir, _) = zero_inbox::lower_file(&input_file, &config)?;
         println!("\n{}\n", "Intermediate Representation:".yellow().bold());
         println!("{:#?}", ir);
         println!();
@@ -184,24 +284,35 @@ fn main() -> Result<()> {
     
     // Visualize the schedule if requested. Because ASCII art never goes out of style.
     if config.visualize_schedule {
-        let ast = zero_inbox::parse_file(&input_file)?;
-        let metadata = config.to_ir_metadata()?;
-        let mut ir = zero_inbox::ir::to_ir(&ast, metadata);
-        zero_inbox::optimizer::optimize(&mut ir);
-        let schedule_mode = config.get_schedule_mode()?;
-        let scheduler = zero_inbox::scheduler::create_scheduler(
-            schedule_mode,
-            if config.deepwork_tag.is_empty() { None } else { Some(config.deepwork_tag.clone()) }
-        );
-        scheduler.schedule(&mut ir);
+        let ir = zero_inbox::build_program(&input_file, &config)?;
         println!("\n{}\n", "Schedule Visualization:".yellow().bold());
         let visualization = visualize_schedule(&ir)?;
         println!("{}", visualization);
         println!();
     }
     
+    // Draw a Gantt chart if requested. For when ASCII art isn't enough.
+    if let Some(chart) = &cli.chart {
+        let ir = zero_inbox::build_program(&input_file, &config)?;
+        if let Some(parent) = chart.parent() {
+            ensure_dir_exists(parent)?;
+        }
+        codegen::render_chart(&ir, chart)?;
+        println!("Chart written to {}", chart.display());
+    }
+    
     // Output: write to file or print to stdout. Your choice.
-    if let Some(output_path) = &config.output_file {
+    let systemd = matches!(config.get_output_format()?, OutputFormat::Systemd);
+    if let (true, Some(output_dir)) = (systemd, &config.output_file) {
+        // Units are one file each, so the output path is a directory
+        let ir = zero_inbox::build_program(&input_file, &config)?;
+        let units = codegen::write_systemd_units(&ir, Path::new(output_dir))?;
+        println!("{} units written to {}", units.written.len(), output_dir);
+        for path in &units.removed {
+            println!("Removed {}", path.display());
+        }
+        println!("Apply them with: {}", units.apply_command());
+    } else if let Some(output_path) = &config.output_file {
         let path = Path::new(output_path);
         // Make sure the directory exists. Because panics are for amateurs.
         if let Some(parent) = path.parent() {
@@ -216,17 +327,8 @@ fn main() -> Result<()> {
     
     // Actually run the plan if you asked for it. Hope you know what you're doing.
     if cli.run {
-        let ast = zero_inbox::parse_file(&input_file)?;
-        let metadata = config.to_ir_metadata()?;
-        let mut ir = zero_inbox::ir::to_ir(&ast, metadata);
-        zero_inbox::optimizer::optimize(&mut ir);
-        let schedule_mode = config.get_schedule_mode()?;
-        let scheduler = zero_inbox::scheduler::create_scheduler(
-            schedule_mode,
-            if config.deepwork_tag.is_empty() { None } else { Some(config.deepwork_tag.clone()) }
-        );
-        scheduler.schedule(&mut ir);
-        runtime::execute(&mut ir, config.dry_run)?;
+        let mut ir = zero_inbox::build_program(&input_file, &config)?;
+        runtime::execute(&mut ir, config.dry_run, &config.get_actions())?;
     }
     Ok(())
 }
@@ -239,6 +341,14 @@ fn show_formats() -> Result<()> {
     println!("  Generates a shell script that can be executed to run your tasks.");
     println!("  Example: zibox plan.zbx --output-format shell --output-file plan.sh");
     
+    println!("\n{}", "posix (.sh)".yellow().bold());
+    println!("  Generates the same script in strict POSIX sh, for dash, busybox and other minimal shells.");
+    println!("  Example: zibox plan.zbx --output-format posix --output-file plan.sh");
+    
+    println!("\n{}", "powershell (.ps1)".yellow().bold());
+    println!("  Generates the same script for PowerShell, with toast notifications on Windows.");
+    println!("  Example: zibox plan.zbx --output-format powershell --output-file plan.ps1");
+    
     println!("\n{}", "markdown (.md)".yellow().bold());
     println!("  Generates a markdown document with your tasks formatted as a checklist.");
     println!("  Example: zibox plan.zbx --output-format markdown --output-file plan.md");
@@ -249,8 +359,145 @@ fn show_formats() -> Result<()> {
     
     println!("\n{}", "calendar (.ics)".yellow().bold());
     println!("  Generates an iCalendar file that can be imported into calendar applications.");
+    println!("  Tags become categories; --calendar-time and --calendar-todos control times and to-dos.");
     println!("  Example: zibox plan.zbx --output-format calendar --output-file plan.ics");
     
+    println!("\n{}", "zbx (.zbx)".yellow().bold());
+    println!("  Generates .zbx source in canonical layout; --annotate adds scheduled times as comments.");
+    println!("  Example: zibox plan.zbx --output-format zbx --annotate --output-file scheduled.zbx");
+    
+    println!("\n{}", "todotxt (.txt)".yellow().bold());
+    println!("  Generates a todo.txt list; zibox reads it back, so plans can move between the two.");
+    println!("  Example: zibox plan.zbx --output-format todotxt --output-file todo.txt");
+    
+    println!("\n{}", "taskwarrior (.json)".yellow().bold());
+    println!("  Generates JSON for `task import`; zibox also reads `task export` output.");
+    println!("  Example: zibox plan.zbx --output-format taskwarrior | task import");
+    
+    println!("\n{}", "org (.org)".yellow().bold());
+    println!("  Generates an Org-mode outline with TODO entries, SCHEDULED timestamps and efforts.");
+    println!("  Example: zibox plan.zbx --output-format org --output-file plan.org");
+    
+    println!("\n{}", "csv (.csv), tsv (.tsv)".yellow().bold());
+    println!("  Generates one row per task for spreadsheets; zibox reads edited sheets back in.");
+    println!("  Example: zibox plan.zbx --output-format csv --output-file plan.csv");
+    
+    println!("\n{}", "html (.html)".yellow().bold());
+    println!("  Generates a self-contained day planner page with a timeline and checkboxes.");
+    println!("  Example: zibox plan.zbx --output-format html --output-file plan.html");
+    
+    println!("\n{}", "mermaid (.mmd), plantuml (.puml)".yellow().bold());
+    println!("  Generates a Gantt diagram as text, with a section per block and dependency links.");
+    println!("  Example: zibox plan.zbx --output-format mermaid --output-file plan.mmd");
+    
+    println!("\n{}", "dot (.dot)".yellow().bold());
+    println!("  Generates a Graphviz dependency graph, clustered by block, with the critical path in bold.");
+    println!("  Example: zibox plan.zbx --output-format dot | dot -Tsvg > plan.svg");
+    
+    println!("\n{}", "systemd (directory)".yellow().bold());
+    println!("  Generates a user timer and service per task that notify you as it starts.");
+    println!("  Example: zibox plan.zbx --output-format systemd --output-file ~/.config/systemd/user");
+    
+    println!("\n{}", "cron (.cron)".yellow().bold());
+    println!("  Generates crontab lines that notify you as each task starts.");
+    println!("  Example: zibox plan.zbx --output-format cron --output-file plan.cron");
+    
+    println!("\n{}", "template".yellow().bold());
+    println!("  Renders the plan with your own template: loops, conditionals and filters.");
+    println!("  Example: zibox plan.zbx --template standup.tmpl --output-file standup.md");
+    
+    Ok(())
+}
+
+fn format_files(files: &[PathBuf], check: bool) -> Result<()> {
+    let mut unformatted = 0;
+    
+    for path in files {
+        let source = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read file {}: {}", path.display(), e))?;
+        let formatted = formatter::format_source(&source);
+        if formatted == source {
+            continue;
+        }
+        
+        if check {
+            println!("{} {}", "Would reformat:".yellow().bold(), path.display());
+            unformatted += 1;
+        } else {
+            fs::write(path, formatted)
+                .map_err(|e| anyhow!("Failed to write file {}: {}", path.display(), e))?;
+            println!("Formatted {}", path.display());
+        }
+    }
+    
+    if unformatted > 0 {
+        return Err(anyhow!("{} file(s) need formatting", unformatted));
+    }
+    Ok(())
+}
+
+fn check_files(files: &[PathBuf], format: &str, config: &Config) -> Result<()> {
+    let mut reports = Vec::new();
+    for path in files {
+        let source = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read file {}: {}", path.display(), e))?;
+        reports.push((path, lint::check_source(&source, config)?));
+    }
+    
+    match format {
+        "json" => {
+            let json: Vec<_> = reports.iter()
+                .map(|(path, diagnostics)| serde_json::json!({
+                    "file": path.display().to_string(),
+                    "diagnostics": diagnostics,
+                }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        },
+        "text" => {
+            for (path, diagnostics) in &reports {
+                for d in diagnostics {
+                    let location = match d.line {
+                        Some(line) => format!("{}:{}", path.display(), line),
+                        None => path.display().to_string(),
+                    };
+                    let severity = match d.severity {
+                        Severity::Error => "error".red().bold(),
+                        Severity::Warning => "warning".yellow().bold(),
+                    };
+                    println!("{}: {}[{}]: {} ({})", location, severity, d.rule, d.message, d.name);
+                }
+            }
+        },
+        other => return Err(anyhow!("Invalid report format: {}", other)),
+    }
+    
+    let errors = reports.iter()
+        .flat_map(|(_, diagnostics)| diagnostics.iter())
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(anyhow!("{} error(s) found", errors));
+    }
+    Ok(())
+}
+
+fn convert_file(input: &Path, to: &str, output: Option<&Path>, config: &Config) -> Result<()> {
+    let (program, mut config) = zero_inbox::lower_file(input, config)?;
+    config.output_format = to.to_string();
+    let converted = codegen::generate_output(&program, config.get_output_format()?)?;
+    
+    match output {
+        Some(path) => {
+            if let Some(parent) = path.parent() {
+                ensure_dir_exists(parent)?;
+            }
+            fs::write(path, &converted)
+                .map_err(|e| anyhow!("Failed to write file {}: {}", path.display(), e))?;
+            println!("Converted {} to {}", input.display(), path.display());
+        },
+        None => print!("{}", converted),
+    }
     Ok(())
 }
 
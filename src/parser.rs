@@ -1,156 +1,338 @@
-use crate::ast::{Block, Task, Priority, TaskDuration};
+use crate::ast::{Block, Task, TaskDuration, Priority, Program, Setting};
 use crate::lexer::{Token, extract_task_params};
+use chrono::NaiveDate;
 use std::str::FromStr;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum ParseError {
     #[error("Unexpected end of file")]
     UnexpectedEOF,
-    
+
     #[error("Unexpected token: {0}")]
     UnexpectedToken(String),
-    
+
     #[error("Invalid duration: {0}")]
     InvalidDuration(String),
-    
+
+    #[error("Invalid due date: {0} (expected YYYY-MM-DD)")]
+    InvalidDate(String),
+
     #[error("Invalid priority: {0}")]
     InvalidPriority(String),
+
+    #[error("Invalid binding: {0}")]
+    InvalidBinding(String),
+
+    #[error("Undefined variable: {0}")]
+    UndefinedVariable(String),
+
+    #[error("Note without a task: {0}")]
+    DanglingNote(String),
+
+    #[error("Invalid label: {0}")]
+    InvalidLabel(String),
+
+    #[error("Duplicate label: {0}")]
+    DuplicateLabel(String),
+}
+
+/// A parse error together with the (1-based) source line it occurred on
+#[derive(Debug, Error)]
+#[error("line {line}: {error}")]
+pub struct LocatedError {
+    pub line: usize,
+    pub error: ParseError,
+}
+
+/// Names declared so far: `let` variables, visible to every line after the
+/// declaration, and task labels, which must be unique across the file
+#[derive(Debug, Default)]
+struct Scope {
+    variables: HashMap<String, String>,
+    labels: HashSet<String>,
+}
+
+impl Scope {
+    fn claim_label(&mut self, label: &str) -> Result<(), ParseError> {
+        if !is_label(label) {
+            return Err(ParseError::InvalidLabel(label.to_string()));
+        }
+        if !self.labels.insert(label.to_string()) {
+            return Err(ParseError::DuplicateLabel(label.to_string()));
+        }
+        Ok(())
+    }
+
+    fn declare(&mut self, name: &str, value: &str) -> Result<(), ParseError> {
+        if !is_variable_name(name) || value.is_empty() {
+            return Err(ParseError::InvalidBinding(format!("let {} = {}", name, value)));
+        }
+        let value = self.resolve(value);
+        self.variables.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Substitute a variable reference with its value; literals pass through.
+    fn resolve(&self, value: &str) -> String {
+        self.variables.get(value).cloned().unwrap_or_else(|| value.to_string())
+    }
+
+    /// Resolve a duration literal or a variable holding one.
+    fn resolve_duration(&self, value: &str) -> Result<TaskDuration, ParseError> {
+        let resolved = self.resolve(value);
+        TaskDuration::from_str(&resolved).map_err(|_| {
+            if is_variable_name(value) && !self.variables.contains_key(value) {
+                ParseError::UndefinedVariable(value.to_string())
+            } else {
+                ParseError::InvalidDuration(resolved.clone())
+            }
+        })
+    }
+}
+
+/// Nests each task under the closest preceding task with a smaller indent
+#[derive(Debug, Default)]
+struct TaskTree {
+    open: Vec<(usize, Task)>,
+}
+
+impl TaskTree {
+    fn push(&mut self, indent: usize, task: Task, block: &mut Block) {
+        self.close_to(indent, block);
+        self.open.push((indent, task));
+    }
+
+    /// Close every open task indented at least as deep as `indent`
+    fn close_to(&mut self, indent: usize, block: &mut Block) {
+        while self.open.last().is_some_and(|(open_indent, _)| *open_indent >= indent) {
+            let (_, task) = self.open.pop().unwrap();
+            match self.open.last_mut() {
+                Some((_, parent)) => parent.add_subtask(task),
+                None => block.add_task(task),
+            }
+        }
+    }
+
+    /// Attach a note to the most recently opened task
+    fn annotate(&mut self, note: &str) -> Result<(), ParseError> {
+        match self.open.last_mut() {
+            Some((_, task)) => {
+                task.add_note(note.to_string());
+                Ok(())
+            },
+            None => Err(ParseError::DanglingNote(note.to_string())),
+        }
+    }
+
+    fn finish(&mut self, block: &mut Block) {
+        self.close_to(0, block);
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn is_label(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+// Names must not start with a digit so they can't be confused with `90m`
+fn is_variable_name(name: &str) -> bool {
+    is_identifier(name) && !name.starts_with(|c: char| c.is_ascii_digit())
 }
 
 pub fn parse(tokens: &[Token]) -> Result<Vec<Block>, ParseError> {
+    parse_program(tokens).map(|program| program.blocks)
+}
+
+/// Parse a token stream into blocks along with any `set` directives.
+pub fn parse_program(tokens: &[Token]) -> Result<Program, ParseError> {
+    parse_located(tokens).map_err(|e| e.error)
+}
+
+/// Like [`parse_program`], but errors carry the line they occurred on.
+pub fn parse_located(tokens: &[Token]) -> Result<Program, LocatedError> {
     let mut i = 0;
+    parse_tokens(tokens, &mut i).map_err(|error| {
+        // Every source line ends in exactly one Newline token
+        let line = tokens[..i.min(tokens.len())].iter()
+            .filter(|t| **t == Token::Newline)
+            .count() + 1;
+        LocatedError { line, error }
+    })
+}
+
+fn parse_tokens(tokens: &[Token], i: &mut usize) -> Result<Program, ParseError> {
     let mut blocks = vec![];
+    let mut settings = vec![];
+    let mut scope = Scope::default();
+    let mut tree = TaskTree::default();
+    let mut indent = 0;
     let mut current_block_name = String::from("default");
-    
+
     // Create a default block to hold tasks that aren't in a specific block
     let mut default_block = Block::new(current_block_name.clone());
-    
-    while i < tokens.len() {
-        match &tokens[i] {
+
+    while *i < tokens.len() {
+        match &tokens[*i] {
             Token::Block(name) => {
                 // If we've collected tasks in the current block, add it to blocks
+                tree.finish(&mut default_block);
                 if !default_block.tasks.is_empty() {
                     blocks.push(default_block);
                     default_block = Block::new(name.clone());
                 }
-                
+
                 current_block_name = name.clone();
                 let mut block = Block::new(current_block_name.clone());
-                
-                i += 1;
-                while i < tokens.len() {
-                    match &tokens[i] {
+
+                *i += 1;
+                while *i < tokens.len() {
+                    match &tokens[*i] {
+                        Token::Indent(width) => {
+                            indent = *width;
+                            *i += 1;
+                        },
                         Token::Task(task_str) => {
-                            let (name, params) = extract_task_params(task_str);
-                            let mut task = Task::new(name).with_params(params);
-                            
-                            i += 1;
-                            
-                            // Process task attributes (duration, tags, priority, dependencies)
-                            let mut tags = HashSet::new();
-                            let mut priority = Priority::default();
-                            let mut depends_on = Vec::new();
-                            let mut duration = None;
-                            
-                            while i < tokens.len() {
-                                match &tokens[i] {
-                                    Token::Duration(dur_str) => {
-                                        duration = Some(FromStr::from_str(dur_str)
-                                            .map_err(|_| ParseError::InvalidDuration(dur_str.clone()))?);
-                                        i += 1;
-                                    },
-                                    Token::Tag(tag) => {
-                                        tags.insert(tag.clone());
-                                        i += 1;
-                                    },
-                                    Token::Priority(prio_str) => {
-                                        priority = FromStr::from_str(prio_str)
-                                            .map_err(|_| ParseError::InvalidPriority(prio_str.clone()))?;
-                                        i += 1;
-                                    },
-                                    Token::DependsOn(deps) => {
-                                        depends_on = deps.clone();
-                                        i += 1;
-                                    },
-                                    Token::Newline | Token::Task(_) | Token::Block(_) | Token::EOF => break,
-                                    other => return Err(ParseError::UnexpectedToken(format!("{:?}", other))),
-                                }
-                            }
-                            
-                            task = task
-                                .with_duration(duration)
-                                .with_tags(tags)
-                                .with_priority(priority)
-                                .with_dependencies(depends_on);
-                                
-                            block.add_task(task);
+                            let task = parse_task(tokens, i, task_str, &mut scope)?;
+                            tree.push(indent, task, &mut block);
+                            indent = 0;
+                        },
+                        Token::Note(note) => {
+                            tree.annotate(note)?;
+                            *i += 1;
+                        },
+                        Token::Let(name, value) => {
+                            scope.declare(name, value)?;
+                            *i += 1;
                         },
-                        Token::Newline => i += 1,
+                        Token::Set(key, value) => {
+                            settings.push(parse_setting(key, value, &scope)?);
+                            *i += 1;
+                        },
+                        Token::Newline => *i += 1,
                         Token::Block(_) | Token::EOF => break,
                         other => return Err(ParseError::UnexpectedToken(format!("{:?}", other))),
                     }
                 }
-                
+
+                tree.finish(&mut block);
                 blocks.push(block);
             },
+            Token::Indent(width) => {
+                indent = *width;
+                *i += 1;
+            },
             Token::Task(task_str) => {
                 // Task outside of a block goes to the default block
-                let (name, params) = extract_task_params(task_str);
-                let mut task = Task::new(name).with_params(params);
-                
-                i += 1;
-                
-                // Process task attributes
-                let mut tags = HashSet::new();
-                let mut priority = Priority::default();
-                let mut depends_on = Vec::new();
-                let mut duration = None;
-                
-                while i < tokens.len() {
-                    match &tokens[i] {
-                        Token::Duration(dur_str) => {
-                            duration = Some(FromStr::from_str(dur_str)
-                                .map_err(|_| ParseError::InvalidDuration(dur_str.clone()))?);
-                            i += 1;
-                        },
-                        Token::Tag(tag) => {
-                            tags.insert(tag.clone());
-                            i += 1;
-                        },
-                        Token::Priority(prio_str) => {
-                            priority = FromStr::from_str(prio_str)
-                                .map_err(|_| ParseError::InvalidPriority(prio_str.clone()))?;
-                            i += 1;
-                        },
-                        Token::DependsOn(deps) => {
-                            depends_on = deps.clone();
-                            i += 1;
-                        },
-                        Token::Newline | Token::Task(_) | Token::Block(_) | Token::EOF => break,
-                        other => return Err(ParseError::UnexpectedToken(format!("{:?}", other))),
-                    }
-                }
-                
-                task = task
-                    .with_duration(duration)
-                    .with_tags(tags)
-                    .with_priority(priority)
-                    .with_dependencies(depends_on);
-                    
-                default_block.add_task(task);
+                let task = parse_task(tokens, i, task_str, &mut scope)?;
+                tree.push(indent, task, &mut default_block);
+                indent = 0;
+            },
+            Token::Note(note) => {
+                tree.annotate(note)?;
+                *i += 1;
+            },
+            Token::Let(name, value) => {
+                scope.declare(name, value)?;
+                *i += 1;
             },
-            Token::Newline => i += 1,
+            Token::Set(key, value) => {
+                settings.push(parse_setting(key, value, &scope)?);
+                *i += 1;
+            },
+            Token::Newline => *i += 1,
             Token::EOF => break,
             other => return Err(ParseError::UnexpectedToken(format!("{:?}", other))),
         }
     }
-    
+
     // Add the default block if it has tasks
+    tree.finish(&mut default_block);
     if !default_block.tasks.is_empty() {
         blocks.push(default_block);
     }
-    
-    Ok(blocks)
+
+    Ok(Program { blocks, settings })
+}
+
+/// Parse a task token and the attribute tokens following it, leaving `i` on
+/// the token that ended the task.
+fn parse_task(tokens: &[Token], i: &mut usize, task_str: &str, scope: &mut Scope) -> Result<Task, ParseError> {
+    let (name, params) = extract_task_params(task_str);
+    let task = Task::new(name).with_params(params);
+
+    *i += 1;
+
+    // Process task attributes (duration, tags, priority, dependencies, due, link, run)
+    let mut tags = HashSet::new();
+    let mut priority = Priority::default();
+    let mut depends_on = Vec::new();
+    let mut duration = None;
+    let mut link = None;
+    let mut due = None;
+    let mut run = None;
+    let mut label = None;
+
+    while *i < tokens.len() {
+        match &tokens[*i] {
+            Token::Duration(dur_str) => {
+                duration = Some(scope.resolve_duration(dur_str)?);
+                *i += 1;
+            },
+            Token::Tag(tag) => {
+                tags.insert(tag.clone());
+                *i += 1;
+            },
+            Token::Priority(prio_str) => {
+                priority = FromStr::from_str(prio_str)
+                    .map_err(|_| ParseError::InvalidPriority(prio_str.clone()))?;
+                *i += 1;
+            },
+            Token::DependsOn(deps) => {
+                depends_on = deps.clone();
+                *i += 1;
+            },
+            Token::Link(url) => {
+                link = Some(url.clone());
+                *i += 1;
+            },
+            Token::Due(date) => {
+                due = Some(NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| ParseError::InvalidDate(date.clone()))?);
+                *i += 1;
+            },
+            Token::Run(command) => {
+                run = Some(command.clone());
+                *i += 1;
+            },
+            Token::Label(name) => {
+                scope.claim_label(name)?;
+                label = Some(name.clone());
+                *i += 1;
+            },
+            Token::Newline | Token::Task(_) | Token::Block(_) | Token::EOF => break,
+            other => return Err(ParseError::UnexpectedToken(format!("{:?}", other))),
+        }
+    }
+
+    Ok(task
+        .with_duration(duration)
+        .with_tags(tags)
+        .with_priority(priority)
+        .with_dependencies(depends_on)
+        .with_link(link)
+        .with_due(due)
+        .with_run(run)
+        .with_label(label))
+}
+
+fn parse_setting(key: &str, value: &str, scope: &Scope) -> Result<Setting, ParseError> {
+    if !is_identifier(key) || value.is_empty() {
+        return Err(ParseError::InvalidBinding(format!("set {} = {}", key, value)));
+    }
+    Ok(Setting::new(key.to_string(), scope.resolve(value)))
 }
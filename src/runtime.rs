@@ -1,13 +1,14 @@
-use crate::ir::{IRProgram, IRTask};
+use crate::actions::Actions;
+use crate::ir::IRProgram;
 use chrono::{Local, Duration};
 use std::io::{self, Write};
+use std::process::{Command, Stdio};
 use std::thread;
 use std::fs::OpenOptions;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use colored::Colorize;
-use log::{info, warn};
 
-pub fn execute(program: &mut IRProgram, dry_run: bool) -> Result<()> {
+pub fn execute(program: &mut IRProgram, dry_run: bool, actions: &Actions) -> Result<()> {
     println!("{}", "Starting Zero Inbox execution".green().bold());
     println!("Press 'q' to quit, 's' to skip a task, or Enter to continue");
     println!();
@@ -49,8 +50,9 @@ pub fn execute(program: &mut IRProgram, dry_run: bool) -> Result<()> {
         // Log task start
         writeln!(
             &mut logbook,
-            "[{}] Starting task: {} ({})",
+            "[{}] Starting task {}: {} ({})",
             Local::now().format("%Y-%m-%d %H:%M:%S"),
+            task_id,
             task_name,
             format_duration(duration)
         )?;
@@ -76,8 +78,9 @@ pub fn execute(program: &mut IRProgram, dry_run: bool) -> Result<()> {
                 println!("{} {}", "Skipping:".yellow().bold(), task_name);
                 writeln!(
                     &mut logbook,
-                    "[{}] Skipped task: {}",
+                    "[{}] Skipped task {}: {}",
                     Local::now().format("%Y-%m-%d %H:%M:%S"),
+                    task_id,
                     task_name
                 )?;
                 continue;
@@ -85,6 +88,34 @@ pub fn execute(program: &mut IRProgram, dry_run: bool) -> Result<()> {
             _ => {}
         }
         
+        // Launch the task's command, if it has one, and carry on timing it
+        match actions.command(program_task) {
+            Some(Ok(command)) if dry_run => {
+                println!("{} {}", "Would run:".yellow().bold(), command.join(" "));
+            },
+            Some(Ok(command)) => {
+                let launched = Command::new(&command[0])
+                    .args(&command[1..])
+                    .stdin(Stdio::null())
+                    .spawn();
+                match launched {
+                    Ok(_) => {
+                        println!("{} {}", "Running:".blue().bold(), command.join(" "));
+                        writeln!(
+                            &mut logbook,
+                            "[{}] Ran for task {}: {}",
+                            Local::now().format("%Y-%m-%d %H:%M:%S"),
+                            task_id,
+                            command.join(" ")
+                        )?;
+                    },
+                    Err(e) => println!("{} {}: {}", "Could not run:".red().bold(), command.join(" "), e),
+                }
+            },
+            Some(Err(e)) => println!("{} {}", "Not running:".yellow().bold(), e),
+            None => {},
+        }
+        
         // Execute the task (simulate with sleep)
         if duration > 0 && !dry_run {
             let start_time = Local::now();
@@ -135,8 +166,9 @@ pub fn execute(program: &mut IRProgram, dry_run: bool) -> Result<()> {
         // Log task completion
         writeln!(
             &mut logbook,
-            "[{}] Completed task: {}",
+            "[{}] Completed task {}: {}",
             Local::now().format("%Y-%m-%d %H:%M:%S"),
+            task_id,
             task_name
         )?;
         
@@ -158,7 +190,7 @@ pub fn execute(program: &mut IRProgram, dry_run: bool) -> Result<()> {
 fn format_duration(minutes: u64) -> String {
     if minutes < 60 {
         format!("{}m", minutes)
-    } else if minutes % 60 == 0 {
+    } else if minutes.is_multiple_of(60) {
         format!("{}h", minutes / 60)
     } else {
         format!("{}h {}m", minutes / 60, minutes % 60)
@@ -1,5 +1,5 @@
-use crate::ir::{IRProgram, IRTask};
-use chrono::{DateTime, Local, Duration, NaiveTime, Timelike};
+use crate::ir::IRProgram;
+use chrono::{Local, Duration};
 use log::{info, warn};
 
 pub enum ScheduleMode {
@@ -9,6 +9,7 @@ pub enum ScheduleMode {
 }
 
 impl ScheduleMode {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "naive" => Some(ScheduleMode::Naive),
@@ -52,8 +53,7 @@ impl Scheduler for NaiveScheduler {
                 let duration_minutes = task.duration_minutes();
                 let end_time = current_time + Duration::minutes(duration_minutes as i64);
                 
-                task.scheduled_start = Some(current_time);
-                task.scheduled_end = Some(end_time);
+                task.set_schedule(current_time, end_time);
                 
                 // Move to the next time slot
                 current_time = end_time;
@@ -109,8 +109,7 @@ impl Scheduler for EarlyBirdScheduler {
             
             // Update the task in the program
             if let Some(program_task) = program.task_map.get_mut(&task.id) {
-                program_task.scheduled_start = Some(current_time);
-                program_task.scheduled_end = Some(end_time);
+                program_task.set_schedule(current_time, end_time);
             }
             
             // Move to the next time slot
@@ -121,8 +120,9 @@ impl Scheduler for EarlyBirdScheduler {
         for block in &mut program.blocks {
             for task in &mut block.tasks {
                 if let Some(program_task) = program.task_map.get(&task.id) {
-                    task.scheduled_start = program_task.scheduled_start;
-                    task.scheduled_end = program_task.scheduled_end;
+                    if let (Some(start), Some(end)) = (program_task.scheduled_start, program_task.scheduled_end) {
+                        task.set_schedule(start, end);
+                    }
                 }
             }
         }
@@ -201,8 +201,7 @@ impl Scheduler for DeepworkScheduler {
             
             // Update the task in the program
             if let Some(program_task) = program.task_map.get_mut(&task.id) {
-                program_task.scheduled_start = Some(current_time);
-                program_task.scheduled_end = Some(end_time);
+                program_task.set_schedule(current_time, end_time);
             }
             
             // Move to the next time slot
@@ -229,8 +228,7 @@ impl Scheduler for DeepworkScheduler {
             
             // Update the task in the program
             if let Some(program_task) = program.task_map.get_mut(&task.id) {
-                program_task.scheduled_start = Some(current_time);
-                program_task.scheduled_end = Some(end_time);
+                program_task.set_schedule(current_time, end_time);
             }
             
             // Move to the next time slot
@@ -241,8 +239,9 @@ impl Scheduler for DeepworkScheduler {
         for block in &mut program.blocks {
             for task in &mut block.tasks {
                 if let Some(program_task) = program.task_map.get(&task.id) {
-                    task.scheduled_start = program_task.scheduled_start;
-                    task.scheduled_end = program_task.scheduled_end;
+                    if let (Some(start), Some(end)) = (program_task.scheduled_start, program_task.scheduled_end) {
+                        task.set_schedule(start, end);
+                    }
                 }
             }
         }
@@ -0,0 +1,308 @@
+//! Lossless, line-oriented syntax tree for .zbx source.
+//!
+//! Every byte of the input ends up in exactly one node, so concatenating the
+//! nodes' text reproduces the source, comments and odd spacing included.
+//! The lexer reads its tokens off this tree and the formatter re-prints it.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxTree {
+    pub nodes: Vec<SyntaxNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    /// Raw source text, line terminators included
+    pub text: String,
+    /// Zero-based line the node starts on
+    pub line: usize,
+    /// Width of the leading whitespace, counting a tab as four columns
+    pub indent: usize,
+    pub kind: NodeKind,
+    /// Trailing `# comment`, without the `#`
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Blank,
+    /// A whole-line comment, without the leading `#`
+    Comment(String),
+    Block(String),
+    Let(String, String),
+    Set(String, String),
+    Task {
+        name: String,
+        attributes: Vec<Attribute>,
+    },
+    /// A `> note` line, or a `"""` note when `quoted` (which may span lines)
+    Note {
+        text: String,
+        quoted: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+    Duration(String),
+    Tag(String),
+    Priority(String),
+    DependsOn(Vec<String>),
+    Label(String),
+    Link(String),
+    /// `due:YYYY-MM-DD`, unchecked
+    Due(String),
+    /// `run:cmd` or `run:"cmd with spaces"`, unquoted
+    Run(String),
+    /// Anything else, kept so tooling can round-trip it
+    Unknown(String),
+}
+
+impl SyntaxTree {
+    pub fn parse(input: &str) -> Self {
+        let mut nodes = Vec::new();
+        let mut lines = input.split_inclusive('\n').enumerate();
+
+        while let Some((line_no, raw)) = lines.next() {
+            let content = raw.trim_end_matches(['\n', '\r']);
+            let trimmed = content.trim();
+            let indent = indent_width(content);
+            let mut text = raw.to_string();
+            let mut comment = None;
+
+            let kind = if trimmed.is_empty() {
+                NodeKind::Blank
+            } else if let Some(body) = trimmed.strip_prefix('#') {
+                NodeKind::Comment(body.trim().to_string())
+            } else if let Some(note) = trimmed.strip_prefix('>') {
+                NodeKind::Note { text: note.trim().to_string(), quoted: false }
+            } else if let Some(rest) = trimmed.strip_prefix("\"\"\"") {
+                // Triple-quoted notes run until the closing quotes
+                let mut note_lines = Vec::new();
+                let mut current = rest;
+                loop {
+                    if let Some(body) = current.strip_suffix("\"\"\"") {
+                        note_lines.push(body.trim().to_string());
+                        break;
+                    }
+                    note_lines.push(current.trim().to_string());
+                    match lines.next() {
+                        Some((_, next)) => {
+                            text.push_str(next);
+                            current = next.trim();
+                        },
+                        None => break,
+                    }
+                }
+
+                let note = note_lines.into_iter()
+                    .skip_while(|l| l.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                NodeKind::Note { text: note.trim_end().to_string(), quoted: true }
+            } else {
+                let (code, trailing) = split_comment(trimmed);
+                comment = trailing.map(|c| c.to_string());
+                classify(code)
+            };
+
+            nodes.push(SyntaxNode { text, line: line_no, indent, kind, comment });
+        }
+
+        Self { nodes }
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            f.write_str(&node.text)?;
+        }
+        Ok(())
+    }
+}
+
+impl SyntaxNode {
+    /// Number of source lines the node covers
+    pub fn line_count(&self) -> usize {
+        self.text.split_inclusive('\n').count().max(1)
+    }
+}
+
+fn classify(code: &str) -> NodeKind {
+    // Block definition
+    if let Some(name) = code.strip_prefix('@') {
+        return NodeKind::Block(name.trim().to_string());
+    }
+
+    // Variable declaration (`let focus = 90m`)
+    if let Some((name, value)) = binding(code, "let") {
+        return NodeKind::Let(name, value);
+    }
+
+    // File-level setting (`set workday_start = 08:00`)
+    if let Some((key, value)) = binding(code, "set") {
+        return NodeKind::Set(key, value);
+    }
+
+    // Task: the name (which might include parameters) and its attributes
+    let mut parts = split_words(code).into_iter();
+    let name = parts.next().unwrap_or_default().to_string();
+    let attributes = parts.map(classify_attribute).collect();
+
+    NodeKind::Task { name, attributes }
+}
+
+fn classify_attribute(part: &str) -> Attribute {
+    if part.starts_with('[') && part.ends_with(']') {
+        Attribute::Duration(part.trim_matches(['[', ']']).to_string())
+    } else if let Some(tag) = part.strip_prefix('#') {
+        Attribute::Tag(tag.to_string())
+    } else if let Some(prio) = part.strip_prefix("p:") {
+        Attribute::Priority(prio.to_string())
+    } else if let Some(deps) = part.strip_prefix("after:") {
+        Attribute::DependsOn(deps.split(',').map(|s| s.to_string()).collect())
+    } else if let Some(label) = part.strip_prefix("id:") {
+        Attribute::Label(label.to_string())
+    } else if let Some(link) = part.strip_prefix("link:") {
+        Attribute::Link(link.to_string())
+    } else if let Some(due) = part.strip_prefix("due:") {
+        Attribute::Due(due.to_string())
+    } else if let Some(command) = part.strip_prefix("run:") {
+        Attribute::Run(unquote(command))
+    } else {
+        Attribute::Unknown(part.to_string())
+    }
+}
+
+/// Split off a trailing comment: a `#` standing on its own, as opposed to a
+/// `#tag` glued to its name or a `#` inside a quoted command.
+fn split_comment(code: &str) -> (&str, Option<&str>) {
+    let bytes = code.as_bytes();
+    let mut quotes = Quotes::default();
+    for (i, &b) in bytes.iter().enumerate() {
+        if quotes.step(bytes, i) {
+            continue;
+        }
+        let starts_word = i == 0 || bytes[i - 1].is_ascii_whitespace();
+        let ends_word = i + 1 == bytes.len() || bytes[i + 1].is_ascii_whitespace();
+        if b == b'#' && starts_word && ends_word {
+            return (code[..i].trim_end(), Some(code[i + 1..].trim()));
+        }
+    }
+    (code, None)
+}
+
+/// Split on whitespace, except inside a quoted command. The quotes stay in
+/// the words; `unquote` takes them off.
+fn split_words(code: &str) -> Vec<&str> {
+    let bytes = code.as_bytes();
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quotes = Quotes::default();
+    for (i, &b) in bytes.iter().enumerate() {
+        if !quotes.step(bytes, i) && b.is_ascii_whitespace() {
+            if let Some(from) = start.take() {
+                words.push(&code[from..i]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(from) = start {
+        words.push(&code[from..]);
+    }
+    words
+}
+
+/// Tracks whether a scan is inside a quoted `run:"..."` command, where a
+/// backslash escapes the next character. Quotes anywhere else are ordinary
+/// characters, so a stray one in a name can't swallow the rest of the line.
+#[derive(Default)]
+struct Quotes {
+    inside: bool,
+    escaped: bool,
+}
+
+impl Quotes {
+    /// Take in the byte at `i`; true if it's part of a quoted command
+    fn step(&mut self, bytes: &[u8], i: usize) -> bool {
+        if self.escaped {
+            self.escaped = false;
+            return true;
+        }
+        match bytes[i] {
+            b'"' if self.inside => {
+                self.inside = false;
+                true
+            },
+            b'"' if opens_command(&bytes[..i]) => {
+                self.inside = true;
+                true
+            },
+            b'\\' if self.inside => {
+                self.escaped = true;
+                true
+            },
+            _ => self.inside,
+        }
+    }
+}
+
+/// Whether a quote after `before` starts a `run:` value
+fn opens_command(before: &[u8]) -> bool {
+    let Some(rest) = before.strip_suffix(b"run:") else {
+        return false;
+    };
+    rest.last().is_none_or(|b| b.is_ascii_whitespace())
+}
+
+/// Take the quotes off a `"quoted value"`, undoing `\"` and `\\`. A bare
+/// value is returned as is, and a missing closing quote is forgiven.
+pub fn unquote(value: &str) -> String {
+    let Some(body) = value.strip_prefix('"') else {
+        return value.to_string();
+    };
+    let mut unquoted = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            '"' => break,
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// The inverse of `unquote`: quote a value if it needs it to stay one word
+pub fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.chars().any(|c| c.is_whitespace() || matches!(c, '"' | '\\' | '#')) {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Width of a line's leading whitespace, counting a tab as four columns
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// A `keyword name = value` line. Anything else starting with the keyword,
+/// like `set up laptop [30m]`, is a task.
+fn binding(code: &str, keyword: &str) -> Option<(String, String)> {
+    let rest = code.strip_prefix(keyword)?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let (name, value) = rest.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some((name.to_string(), value.trim().to_string()))
+}
@@ -29,11 +29,19 @@ pub fn slugify(input: &str) -> String {
     result
 }
 
+/// FNV-1a hash of a string, stable across runs, platforms and Rust versions
+pub fn stable_hash(input: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    
+    input.bytes().fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
 /// Format a duration in minutes to a human-readable string
 pub fn format_duration(minutes: u64) -> String {
     if minutes < 60 {
         format!("{}m", minutes)
-    } else if minutes % 60 == 0 {
+    } else if minutes.is_multiple_of(60) {
         format!("{}h", minutes / 60)
     } else {
         format!("{}h {}m", minutes / 60, minutes % 60)
@@ -0,0 +1,864 @@
+use zero_inbox::{
+    actions::Actions,
+    ast::Priority,
+    codegen::{generate_output, render_chart, write_systemd_units, CalendarOptions, CalendarTime, OutputFormat},
+    config::Config,
+    formatter,
+    import::{self, InputFormat},
+    ir::{self, IRMetadata, IRProgram, IRTask},
+    lexer, parser,
+};
+
+fn lower(source: &str) -> IRProgram {
+    let blocks = parser::parse(&lexer::tokenize(source)).unwrap();
+    ir::to_ir(&blocks, IRMetadata::default())
+}
+
+/// 09:00 local time on a fixed day, for tests that schedule by hand
+fn at_nine() -> chrono::DateTime<chrono::Local> {
+    chrono::NaiveDate::from_ymd_opt(2026, 10, 16)
+        .unwrap()
+        .and_hms_opt(9, 0, 0)
+        .unwrap()
+        .and_local_timezone(chrono::Local)
+        .unwrap()
+}
+
+/// Everything the source can express, subtasks included
+fn summary(program: &IRProgram) -> Vec<String> {
+    fn describe(task: &IRTask, out: &mut Vec<String>) {
+        let mut tags: Vec<_> = task.tags.iter().collect();
+        tags.sort();
+        out.push(format!(
+            "{} {} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+            task.block, task.id, task.display_name(), task.duration, task.priority,
+            tags, task.depends_on, task.due, task.notes, task.link
+        ));
+        for subtask in &task.subtasks {
+            describe(subtask, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    for task in program.blocks.iter().flat_map(|b| b.tasks.iter()) {
+        describe(task, &mut out);
+    }
+    out
+}
+
+#[test]
+fn test_zbx_round_trip() {
+    let source = "\
+@morning
+  write(report) p:high #deepwork
+    outline [30m]
+    draft [90m] link:https://docs.example/draft
+      > keep it short
+  review(report) [30m] after:draft,write due:2026-10-20
+  review(report) [15m] after:review(report)
+
+@evening
+  reflect [1h] p:low #journal id:reflection
+    \"\"\"
+    What went well?
+    What didn't?
+    \"\"\"
+  plan [15m] after:reflection
+";
+    let mut program = lower(source);
+    // Depend on the second review, which no name in the source can reach
+    let second_review = program.blocks[0].tasks[2].id.clone();
+    program.blocks[1].tasks[1].depends_on.push(second_review.clone());
+    let emitted = generate_output(&program, OutputFormat::Zbx { annotate: false }).unwrap();
+
+    assert!(formatter::is_formatted(&emitted), "{}", emitted);
+    assert_eq!(summary(&lower(&emitted)), summary(&program), "{}", emitted);
+
+    // ...so it keeps its ID as a label
+    assert!(emitted.contains(&format!("after:reflection,{}", second_review)), "{}", emitted);
+    assert!(emitted.contains(&format!("after:review id:{}", second_review)), "{}", emitted);
+    // A derived parent duration isn't written out
+    assert!(emitted.contains("  write(report) p:high #deepwork\n"), "{}", emitted);
+}
+
+#[test]
+fn test_zbx_keeps_settings() {
+    let mut program = lower("@morning\n  a [1h] #deepwork\n");
+    program.metadata.workday_start = chrono::NaiveTime::from_hms_opt(8, 30, 0).unwrap();
+    program.metadata.workday_end = chrono::NaiveTime::from_hms_opt(16, 0, 0).unwrap();
+    program.metadata.max_parallel = 2;
+    program.metadata.focus_tags = vec!["deepwork".to_string(), "writing".to_string()];
+
+    let emitted = generate_output(&program, OutputFormat::Zbx { annotate: false }).unwrap();
+    assert!(formatter::is_formatted(&emitted), "{}", emitted);
+    assert!(emitted.starts_with("set workday_start = 08:30\n"), "{}", emitted);
+
+    let (reparsed, _) = import::import(&emitted, InputFormat::Zbx, &Config::default()).unwrap();
+    assert_eq!(reparsed.metadata.workday_start, program.metadata.workday_start);
+    assert_eq!(reparsed.metadata.workday_end, program.metadata.workday_end);
+    assert_eq!(reparsed.metadata.max_parallel, 2);
+    assert_eq!(reparsed.metadata.focus_tags, program.metadata.focus_tags);
+    assert_eq!(summary(&reparsed), summary(&program));
+
+    // Defaults aren't spelled out
+    let plain = generate_output(&lower("@morning\n  a [1h]\n"), OutputFormat::Zbx { annotate: false }).unwrap();
+    assert!(!plain.contains("set "), "{}", plain);
+}
+
+#[test]
+fn test_zbx_annotations() {
+    let mut program = lower("@morning\n  a [1h]\n  b [30m]\n");
+    let start = chrono::Local::now()
+        .date_naive()
+        .and_hms_opt(9, 0, 0)
+        .unwrap()
+        .and_local_timezone(chrono::Local)
+        .unwrap();
+    program.blocks[0].tasks[0] = program.blocks[0].tasks[0].clone().with_scheduled_time(start);
+    program.blocks[0].tasks[0].completed = true;
+
+    let emitted = generate_output(&program, OutputFormat::Zbx { annotate: true }).unwrap();
+    assert_eq!(emitted, "@morning\n  a [1h]  # 09:00-10:00, done\n  b [30m]\n");
+    assert_eq!(summary(&lower(&emitted)), summary(&program));
+}
+
+#[test]
+fn test_todotxt_round_trip() {
+    let source = "\
+@morning
+  write(report) p:critical #deepwork #writing
+    outline [30m]
+    draft [90m] link:https://docs.example/draft
+  review(report) [30m] after:draft,write due:2026-10-20
+
+@evening
+  reflect [1h] p:low #journal id:reflection
+    > what went well, 100%?
+    \"\"\"
+    first line
+    second line
+    \"\"\"
+  plan [15m] after:reflection
+";
+    let mut program = lower(source);
+    program.blocks[0].tasks[0].subtasks[0].completed = true;
+    let emitted = generate_output(&program, OutputFormat::TodoTxt).unwrap();
+
+    let lines: Vec<&str> = emitted.lines().collect();
+    let write = &program.blocks[0].tasks[0];
+    assert_eq!(lines[0], format!("(A) write(report) +deepwork +writing @morning id:{}", write.id));
+    assert_eq!(lines[1], format!("x outline @morning pri:C dur:30m parent:{}", write.id));
+    assert!(lines[3].ends_with(" due:2026-10-20"), "{}", lines[3]);
+    assert_eq!(
+        lines[4],
+        "(D) reflect +journal @evening dur:1h id:reflection note:what%20went%20well,%20100%25? note:first%20line%0Asecond%20line"
+    );
+    assert_eq!(lines[5], "(C) plan @evening dur:15m after:reflection");
+
+    let (imported, _) = import::import(&emitted, InputFormat::TodoTxt, &Config::default()).unwrap();
+    assert_eq!(summary(&imported), summary(&program), "{}", emitted);
+    assert!(imported.blocks[0].tasks[0].subtasks[0].completed);
+    assert!(!imported.blocks[0].tasks[0].subtasks[1].completed);
+}
+
+#[test]
+fn test_taskwarrior_export() {
+    let source = "\
+@morning
+  write(report) p:critical #deepwork
+    outline [30m]
+    draft [90m] link:https://docs.example/draft
+      > keep it short
+  review [1h] p:low after:write due:2026-10-20
+";
+    let program = lower(source);
+    let emitted = generate_output(&program, OutputFormat::Taskwarrior).unwrap();
+    let tasks: Vec<serde_json::Value> = serde_json::from_str(&emitted).unwrap();
+    assert_eq!(tasks.len(), 4);
+
+    let (write, outline, draft, review) = (&tasks[0], &tasks[1], &tasks[2], &tasks[3]);
+    assert_eq!(write["description"], "write(report)");
+    assert_eq!(write["priority"], "H");
+    assert_eq!(write["zibox_priority"], "critical");
+    assert_eq!(write["project"], "morning");
+    assert_eq!(write["tags"], serde_json::json!(["deepwork"]));
+    // A parent waits for its subtasks, which name it
+    assert_eq!(write["depends"], serde_json::json!([outline["uuid"], draft["uuid"]]));
+    assert_eq!(write["estimate"], "PT2H");
+    assert!(write.get("zibox_parent").is_none());
+    assert_eq!(outline["zibox_parent"], write["uuid"]);
+
+    assert_eq!(outline["estimate"], "PT30M");
+    assert_eq!(draft["estimate"], "PT1H30M");
+    let annotations: Vec<&serde_json::Value> = draft["annotations"].as_array().unwrap()
+        .iter().map(|a| &a["description"]).collect();
+    assert_eq!(annotations, vec!["keep it short", "https://docs.example/draft"]);
+
+    assert_eq!(review["priority"], "L");
+    assert_eq!(review["status"], "pending");
+    assert_eq!(review["depends"], serde_json::json!([write["uuid"]]));
+    assert!(review.get("zibox_priority").is_none());
+    // Due dates are midnight, and the slot isn't one
+    let midnight = chrono::NaiveDate::from_ymd_opt(2026, 10, 20).unwrap()
+        .and_hms_opt(0, 0, 0).unwrap()
+        .and_local_timezone(chrono::Local).unwrap()
+        .with_timezone(&chrono::Utc);
+    assert_eq!(review["due"], midnight.format("%Y%m%dT%H%M%SZ").to_string());
+
+    // Reading it back restores what Taskwarrior can't hold
+    let (imported, _) = import::import(&emitted, InputFormat::Taskwarrior, &Config::default()).unwrap();
+    let tasks = &imported.blocks[0].tasks;
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].priority, Priority::Critical);
+    assert_eq!(tasks[0].duration_minutes(), 120);
+    assert!(tasks[0].depends_on.is_empty());
+    let subtasks: Vec<&str> = tasks[0].subtasks.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(subtasks, vec!["outline", "draft"]);
+    assert_eq!(tasks[1].due, chrono::NaiveDate::from_ymd_opt(2026, 10, 20));
+
+    // UUIDs are derived from task IDs, so they're the same every time
+    let again: Vec<serde_json::Value> = serde_json::from_str(
+        &generate_output(&lower(source), OutputFormat::Taskwarrior).unwrap()
+    ).unwrap();
+    assert_eq!(again[3]["uuid"], review["uuid"]);
+    let uuid = review["uuid"].as_str().unwrap();
+    assert_eq!(uuid.len(), 36);
+    assert_eq!(&uuid[14..15], "8");
+}
+
+#[test]
+fn test_org_output() {
+    let mut program = lower("\
+@morning
+  write(report) p:critical #deepwork
+    outline [30m]
+    draft [90m] link:https://docs.example/draft
+      > keep it short
+  review [20m] p:low #admin
+");
+    let start = at_nine();
+    let block = &mut program.blocks[0];
+    block.tasks[0].set_schedule(start, start + chrono::Duration::hours(2));
+    block.tasks[0].subtasks[0].completed = true;
+    block.tasks[1] = block.tasks[1].clone().with_scheduled_time(start + chrono::Duration::hours(2));
+
+    let emitted = generate_output(&program, OutputFormat::Org).unwrap();
+    let body: Vec<&str> = emitted.lines().filter(|line| !line.starts_with("#+DATE:")).collect();
+    assert_eq!(body.join("\n"), "\
+#+TITLE: Zero Inbox Plan
+#+PRIORITIES: A D C
+
+* morning
+** TODO [#A] write(report)                                         :deepwork:
+SCHEDULED: <2026-10-16 Fri 09:00-11:00>
+*** DONE [#C] outline
+SCHEDULED: <2026-10-16 Fri 09:00-09:30>
+:PROPERTIES:
+:EFFORT:   0:30
+:END:
+*** TODO [#C] draft
+SCHEDULED: <2026-10-16 Fri 09:30-11:00>
+:PROPERTIES:
+:EFFORT:   1:30
+:END:
+keep it short
+[[https://docs.example/draft]]
+** TODO [#D] review                                                   :admin:
+SCHEDULED: <2026-10-16 Fri 11:00-11:20>
+:PROPERTIES:
+:EFFORT:   0:20
+:END:");
+}
+
+#[test]
+fn test_csv_round_trip() {
+    let source = "\
+@morning
+  write(report) p:critical #deepwork #writing
+    outline [30m]
+    draft [90m] link:https://docs.example/draft?a=1,b=2
+      > keep it \"short\", really
+      > and clear
+  review(report) [30m] after:draft,write
+
+@evening
+  reflect [1h] p:low #journal id:reflection
+  plan [15m] after:reflection
+";
+    let mut program = lower(source);
+    program.blocks[1].tasks[0].completed = true;
+    let emitted = generate_output(&program, OutputFormat::Csv { delimiter: b',' }).unwrap();
+
+    let write = &program.blocks[0].tasks[0];
+    let draft = &write.subtasks[1];
+    let lines: Vec<&str> = emitted.lines().collect();
+    assert_eq!(lines[0], "id,block,name,params,duration,priority,tags,deps,start,end,completed,parent,link,notes");
+    assert_eq!(lines[1], format!("{},morning,write,report,,critical,\"deepwork,writing\",,,,false,,,", write.id));
+    assert_eq!(
+        lines[3],
+        format!("{},morning,draft,,90,medium,,,,,false,{},\"https://docs.example/draft?a=1,b=2\",\"keep it \"\"short\"\", really", draft.id, write.id)
+    );
+    assert!(emitted.contains(",true,,,\n"), "{}", emitted);
+
+    for (format, delimiter) in [(InputFormat::Csv { delimiter: b',' }, b','), (InputFormat::Csv { delimiter: b'\t' }, b'\t')] {
+        let emitted = generate_output(&program, OutputFormat::Csv { delimiter }).unwrap();
+        let (imported, _) = import::import(&emitted, format, &Config::default()).unwrap();
+        assert_eq!(summary(&imported), summary(&program), "{}", emitted);
+        assert!(imported.blocks[1].tasks[0].completed);
+    }
+}
+
+#[test]
+fn test_html_output() {
+    let mut program = lower("\
+@morning
+  write(report) [1h] p:critical #deepwork
+    > <script>alert(\"hi\")</script> & more
+  review [30m] p:low after:write link:https://docs.example/?a=1&b=2
+");
+    let start = at_nine();
+    let block = &mut program.blocks[0];
+    block.tasks[0] = block.tasks[0].clone().with_scheduled_time(start);
+    block.tasks[0].completed = true;
+    block.tasks[1] = block.tasks[1].clone().with_scheduled_time(start + chrono::Duration::hours(1));
+    let write = block.tasks[0].id.clone();
+
+    let html = generate_output(&program, OutputFormat::Html).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    // Self-contained and free of terminal colours
+    assert!(!html.contains('\x1b'));
+    assert!(!html.contains("<link") && !html.contains(" src="));
+    assert_eq!(html.matches("<script>").count(), 1);
+
+    // Timeline bars, scaled to the 09:00-10:30 span (rounded out to whole hours)
+    assert!(html.contains(&format!("href=\"#{}\" data-task=\"{}\" style=\"left:0.00%;width:50.00%\"", write, write)), "{}", html);
+    assert!(html.contains("style=\"left:50.00%;width:25.00%\""), "{}", html);
+    assert!(html.contains("<span style=\"left:100.00%\">11:00</span>"), "{}", html);
+
+    assert!(html.contains(&format!("<input type=\"checkbox\" data-task=\"{}\" checked>", write)));
+    assert!(html.contains("<li class=\"task p-critical\""));
+    assert!(html.contains("<span class=\"time\">10:00–10:30</span><span class=\"name\">review</span>"));
+    assert!(html.contains("class=\"tag\" style=\"background:hsl("));
+    assert!(html.contains(&format!("After: <a href=\"#{}\">write(report)</a>", write)));
+    assert!(html.contains("localStorage"));
+    // Ticks are remembered per plan date
+    assert!(html.contains("<body data-plan=\"2026-10-16\">"), "{}", html);
+    assert!(html.contains("'zibox:' + document.body.dataset.plan + ':' + box.dataset.task"));
+
+    // Everything from the plan is escaped
+    assert!(html.contains("&lt;script&gt;alert(&quot;hi&quot;)&lt;/script&gt; &amp; more"));
+    assert!(html.contains("<a href=\"https://docs.example/?a=1&amp;b=2\">"));
+
+    // Only web and mail links are clickable
+    program.blocks[0].tasks[1].link = Some("javascript:alert(1)".to_string());
+    program.blocks[0].tasks[0].link = Some("MAILTO:me@example.com".to_string());
+    let html = generate_output(&program, OutputFormat::Html).unwrap();
+    assert!(html.contains("<div class=\"link\">javascript:alert(1)</div>"), "{}", html);
+    assert!(!html.contains("href=\"javascript:"));
+    assert!(html.contains("<a href=\"MAILTO:me@example.com\">"));
+}
+
+#[test]
+fn test_gantt_text_output() {
+    let mut program = lower("\
+@morning
+  write(report) [1h] p:critical
+  review [30m] after:write
+  file(a:b;c) [15m] p:low
+
+@afternoon
+  plan [45m]
+");
+    let start = at_nine();
+    let block = &mut program.blocks[0];
+    block.tasks[0] = block.tasks[0].clone().with_scheduled_time(start);
+    block.tasks[0].completed = true;
+    block.tasks[1] = block.tasks[1].clone().with_scheduled_time(start + chrono::Duration::hours(1));
+    block.tasks[2] = block.tasks[2].clone().with_scheduled_time(start + chrono::Duration::minutes(100));
+    block.tasks[2].id = "file-notes".to_string();
+    let write = block.tasks[0].id.clone();
+    let review = block.tasks[1].id.clone();
+    let plan = program.blocks[1].tasks[0].id.clone();
+    // Lookups go through the task map, so rebuild it with the new times
+    let program = IRProgram::new(program.blocks, program.metadata);
+
+    let mermaid = generate_output(&program, OutputFormat::Mermaid).unwrap();
+    let lines: Vec<&str> = mermaid.lines().collect();
+    assert_eq!(lines[0], "gantt");
+    assert!(lines.contains(&"    section morning"));
+    assert!(lines.contains(&"    section afternoon"));
+    assert!(lines.contains(&format!("    write(report) :crit, done, {}, 2026-10-16 09:00, 60m", write).as_str()), "{}", mermaid);
+    // Starts right as its dependency ends, so it's linked rather than placed
+    assert!(lines.contains(&format!("    review :{}, after {}, 30m", review, write).as_str()), "{}", mermaid);
+    assert!(lines.contains(&"    file(a#58;b#59;c) :file_notes, 2026-10-16 10:40, 15m"), "{}", mermaid);
+    // Unscheduled tasks follow the one before them
+    assert!(lines.contains(&format!("    plan :{}, 45m", plan).as_str()), "{}", mermaid);
+
+    // Columns of 15 minutes, the largest step every length fits, and no
+    // made-up dates: tasks follow each other in plan order
+    let plantuml = generate_output(&program, OutputFormat::PlantUml).unwrap();
+    let lines: Vec<&str> = plantuml.lines().collect();
+    assert_eq!(lines[0], "@startgantt");
+    assert_eq!(lines.last(), Some(&"@endgantt"));
+    assert!(lines.contains(&"caption One column is 15m; tasks are drawn in plan order, without clock times"), "{}", plantuml);
+    assert!(!plantuml.contains("2026"), "{}", plantuml);
+    assert!(lines.contains(&"-- morning --"));
+    assert!(lines.contains(&format!("[write(report)] as [{}] lasts 4 days", write).as_str()), "{}", plantuml);
+    assert!(!plantuml.contains(&format!("[{}] starts", write)), "{}", plantuml);
+    assert!(lines.contains(&format!("[{}] is colored in Red", write).as_str()));
+    assert!(lines.contains(&format!("[{}] is 100% completed", write).as_str()));
+    assert!(lines.contains(&format!("[{}] starts at [{}]'s end", review, write).as_str()), "{}", plantuml);
+    assert!(lines.contains(&format!("[file_notes] starts at [{}]'s end", review).as_str()), "{}", plantuml);
+    assert!(lines.contains(&format!("[plan] as [{}] lasts 3 days", plan).as_str()), "{}", plantuml);
+    assert!(lines.contains(&format!("[{}] starts at [file_notes]'s end", plan).as_str()), "{}", plantuml);
+
+    // Subtasks start with their parent, and a milestone at the very start
+    // waits for the first bar
+    let program = lower("@a\n  kickoff\n  write [1h]\n    outline [20m]\n    draft [40m]\n");
+    let write = program.blocks[0].tasks[1].id.clone();
+    let outline = program.blocks[0].tasks[1].subtasks[0].id.clone();
+    let draft = program.blocks[0].tasks[1].subtasks[1].id.clone();
+    let plantuml = generate_output(&program, OutputFormat::PlantUml).unwrap();
+    let lines: Vec<&str> = plantuml.lines().collect();
+    assert!(lines.contains(&format!("[kickoff] happens at [{}]'s start", write).as_str()), "{}", plantuml);
+    assert!(lines.contains(&format!("[{}] starts at [{}]'s start", outline, write).as_str()), "{}", plantuml);
+    assert!(lines.contains(&format!("[{}] starts at [{}]'s end", draft, outline).as_str()), "{}", plantuml);
+    let error = generate_output(&lower("@a\n  kickoff\n"), OutputFormat::PlantUml).unwrap_err();
+    assert!(error.to_string().contains("no task has a duration"));
+}
+
+#[test]
+fn test_dot_output() {
+    let mut program = lower("\
+@morning
+  write(report) [1h] p:critical
+  email [2h] p:low
+  review [30m] after:write
+
+@afternoon
+  \"quoted\" [15m]
+  plan [45m] p:high after:review
+    outline [10m]
+");
+    program.blocks[0].tasks[1].completed = true;
+    let program = IRProgram::new(program.blocks, program.metadata);
+    let id = |block: usize, task: usize| program.blocks[block].tasks[task].id.clone();
+    let (write, email, review, plan) = (id(0, 0), id(0, 1), id(0, 2), id(1, 1));
+    let outline = program.blocks[1].tasks[1].subtasks[0].id.clone();
+
+    let dot = generate_output(&program, OutputFormat::Dot).unwrap();
+    assert!(dot.starts_with("digraph zibox {"), "{}", dot);
+    assert!(dot.contains("subgraph cluster_0 {\n    label=\"morning\";"), "{}", dot);
+    assert!(dot.contains("subgraph cluster_1 {\n    label=\"afternoon\";"), "{}", dot);
+
+    // Priority as colour, duration in the label, done tasks greyed out
+    assert!(dot.contains(&format!("\"{}\" [label=\"write(report)\\n1h\", fillcolor=\"#c62828\", color=black, penwidth=3];", write)), "{}", dot);
+    assert!(dot.contains(&format!("\"{}\" [label=\"email\\n2h\", fillcolor=\"#bdbdbd\"];", email)), "{}", dot);
+    assert!(dot.contains("[label=\"\\\"quoted\\\"\\n15m\", fillcolor=\"#1565c0\"];"), "{}", dot);
+
+    // write → review → plan is 2h 15m, longer than email on its own
+    assert!(dot.contains("label=\"Critical path (bold): 2h 15m\";"), "{}", dot);
+    assert!(dot.contains(&format!("\"{}\" -> \"{}\" [color=black, penwidth=3];", write, review)), "{}", dot);
+    assert!(dot.contains(&format!("\"{}\" -> \"{}\" [color=black, penwidth=3];", review, plan)), "{}", dot);
+    assert!(dot.contains(&format!("\"{}\" -> \"{}\" [style=dashed, arrowhead=none];", plan, outline)), "{}", dot);
+    assert!(dot.trim_end().ends_with('}'));
+}
+
+#[test]
+fn test_calendar_output() {
+    let mut program = lower("\
+@morning
+  write(report) [1h] p:critical #writing #deepwork
+    outline [30m]
+  review [30m] p:low
+  email [15m] p:high
+");
+    let start = at_nine();
+    let block = &mut program.blocks[0];
+    block.tasks[0] = block.tasks[0].clone().with_scheduled_time(start);
+    block.tasks[1] = block.tasks[1].clone().with_scheduled_time(start + chrono::Duration::hours(1));
+    block.tasks[2].completed = true;
+    let write = program.blocks[0].tasks[0].id.clone();
+    let email = program.blocks[0].tasks[2].id.clone();
+
+    // By default: UTC times, no to-dos, no reminders
+    let ics = generate_output(&program, OutputFormat::Calendar(CalendarOptions::default())).unwrap();
+    let lines: Vec<&str> = ics.lines().collect();
+    assert_eq!(lines.iter().filter(|l| **l == "BEGIN:VEVENT").count(), 2, "{}", ics);
+    assert!(!ics.contains("VTODO") && !ics.contains("VALARM"), "{}", ics);
+    assert!(lines.contains(&format!("UID:{}-20261016@zero-inbox", write).as_str()), "{}", ics);
+    assert!(lines.contains(&"CATEGORIES:deepwork,writing"), "{}", ics);
+    assert!(lines.contains(&"PRIORITY:1") && lines.contains(&"PRIORITY:9"), "{}", ics);
+    let utc = start.with_timezone(&chrono::Utc).format("DTSTART:%Y%m%dT%H%M%SZ").to_string();
+    assert!(lines.contains(&utc.as_str()), "{}", ics);
+    // Every entry shares one DTSTAMP
+    let stamps: std::collections::HashSet<&&str> = lines.iter().filter(|l| l.starts_with("DTSTAMP:")).collect();
+    assert_eq!(stamps.len(), 1, "{}", ics);
+    // Subtasks are listed in their parent's description
+    assert!(lines.contains(&"DESCRIPTION:Block: morning\\n\\nSubtasks:\\n- outline"), "{}", ics);
+
+    // Zoned times come with a definition of the zone, ahead of the events
+    let options = CalendarOptions { time: CalendarTime::Zoned, ..CalendarOptions::default() };
+    let ics = generate_output(&program, OutputFormat::Calendar(options)).unwrap();
+    let lines: Vec<&str> = ics.lines().collect();
+    let tzid = lines.iter().find_map(|l| l.strip_prefix("DTSTART;TZID=")).unwrap().split(':').next().unwrap();
+    let definition = lines.iter().position(|l| *l == "BEGIN:VTIMEZONE").expect(&ics);
+    assert_eq!(lines[definition + 1], format!("TZID:{}", tzid), "{}", ics);
+    assert!(definition < lines.iter().position(|l| *l == "BEGIN:VEVENT").unwrap(), "{}", ics);
+    let offset = start.offset().local_minus_utc() / 60;
+    let offset = format!("TZOFFSETTO:{}{:02}{:02}", if offset < 0 { '-' } else { '+' }, offset.abs() / 60, offset.abs() % 60);
+    assert!(lines.contains(&offset.as_str()), "{}", ics);
+
+    let options = CalendarOptions {
+        time: CalendarTime::Floating,
+        todos: true,
+        alarms: vec![(Priority::Critical, 15)],
+    };
+    let ics = generate_output(&program, OutputFormat::Calendar(options)).unwrap();
+    let lines: Vec<&str> = ics.lines().collect();
+    assert!(lines.contains(&"DTSTART:20261016T090000"), "{}", ics);
+    assert!(lines.contains(&"DTEND:20261016T100000"), "{}", ics);
+    // One reminder, for the critical task
+    assert_eq!(lines.iter().filter(|l| **l == "BEGIN:VALARM").count(), 1, "{}", ics);
+    assert!(lines.contains(&"TRIGGER;RELATED=START:-PT900S"), "{}", ics);
+    // The unscheduled task comes along as a finished to-do
+    assert!(lines.contains(&format!("UID:{}@zero-inbox", email).as_str()), "{}", ics);
+    assert!(lines.contains(&"BEGIN:VTODO") && lines.contains(&"STATUS:COMPLETED"), "{}", ics);
+    assert!(lines.contains(&"PERCENT-COMPLETE:100"), "{}", ics);
+}
+
+#[test]
+fn test_shell_script_is_inert() {
+    let dir = tempfile::tempdir().unwrap();
+    let hostile = [
+        "$(touch pwned)",
+        "`touch pwned`",
+        "\"; touch pwned; echo \"",
+        "'; touch pwned; echo '",
+        "it's ${HOME} \\n -e",
+        "line\ntouch pwned",
+    ];
+    let mut program = lower("@morning\n  a\n  b\n  c\n  d\n  e\n  f\n");
+    program.blocks[0].name = "$(touch pwned)".to_string();
+    for (task, name) in program.blocks[0].tasks.iter_mut().zip(hostile) {
+        task.name = name.to_string();
+    }
+
+    let script = generate_output(&program, OutputFormat::Shell(Actions::default())).unwrap();
+    assert!(script.contains("set -euo pipefail"), "{}", script);
+    // Comments can't be broken out of
+    assert!(script.lines().all(|line| line != "touch pwned"), "{}", script);
+
+    // No notification tools on the PATH, so notify falls back to printing
+    let run = std::process::Command::new("/bin/bash")
+        .arg("-c")
+        .arg(&script)
+        .current_dir(dir.path())
+        .env("PATH", "")
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    assert!(!dir.path().join("pwned").exists(), "{}", script);
+
+    let stdout = String::from_utf8(run.stdout).unwrap();
+    assert!(stdout.starts_with("[$(touch pwned)]\n"), "{}", stdout);
+    for name in hostile {
+        assert!(stdout.contains(&format!("[??:??] Starting: {}\n", name)), "{}", stdout);
+        assert!(stdout.contains(&format!("Task Complete: {}\n", name)), "{}", stdout);
+    }
+    assert!(stdout.ends_with("Zero Inbox: All tasks completed!\n"), "{}", stdout);
+}
+
+#[test]
+fn test_shell_actions() {
+    let dir = tempfile::tempdir().unwrap();
+    let bin = dir.path().join("bin");
+    std::fs::create_dir(&bin).unwrap();
+    let mark = bin.join("mark");
+    std::fs::write(&mark, "#!/bin/sh\nprintf '%s|' \"$@\" > marked\n").unwrap();
+    std::fs::set_permissions(&mark, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+
+    let program = lower("\
+@morning
+  note(it's;$HOME) run:\"mark {1} 'two words'\"
+  wipe run:\"rm -rf marked\"
+");
+    let actions = Actions { allow: vec!["mark".to_string()], ..Actions::default() };
+    let script = generate_output(&program, OutputFormat::Shell(actions)).unwrap();
+    assert!(script.contains("run_action 'mark' 'it'\\''s;$HOME' 'two words'"), "{}", script);
+    assert!(script.contains("# Not run: rm is not in the allow-list"), "{}", script);
+
+    let run = |dry_run: bool| {
+        std::process::Command::new("/bin/bash")
+            .arg("-c")
+            .arg(&script)
+            .current_dir(dir.path())
+            .env("PATH", &bin)
+            .env("ZIBOX_DRY_RUN", if dry_run { "1" } else { "" })
+            .output()
+            .unwrap()
+    };
+
+    let dry = run(true);
+    assert!(dry.status.success(), "{}", String::from_utf8_lossy(&dry.stderr));
+    let stdout = String::from_utf8(dry.stdout).unwrap();
+    assert!(stdout.contains("Would run: mark it\\'s\\;\\$HOME two\\ words\n"), "{}", stdout);
+    assert!(!dir.path().join("marked").exists());
+
+    // Commands run in the background, so give it a moment
+    let wet = run(false);
+    assert!(wet.status.success(), "{}", String::from_utf8_lossy(&wet.stderr));
+    let marked = dir.path().join("marked");
+    for _ in 0..50 {
+        if std::fs::read_to_string(&marked).is_ok_and(|m| m.ends_with('|')) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    assert_eq!(std::fs::read_to_string(&marked).unwrap(), "it's;$HOME|two words|");
+}
+
+#[test]
+fn test_posix_and_powershell_scripts() {
+    let dir = tempfile::tempdir().unwrap();
+    let hostile = ["$(touch pwned)", "it's `touch pwned`", "\u{2019}; touch pwned; \u{2018}"];
+    let mut program = lower("@morning\n  a run:\"echo {1}\"\n  b\n  c\n");
+    for (task, name) in program.blocks[0].tasks.iter_mut().zip(hostile) {
+        task.name = name.to_string();
+    }
+    program.blocks[0].tasks[0].params = vec!["x'y".to_string()];
+    let actions = Actions { allow: vec!["echo".to_string()], ..Actions::default() };
+
+    let script = generate_output(&program, OutputFormat::Posix(actions.clone())).unwrap();
+    assert!(script.starts_with("#!/bin/sh\n"), "{}", script);
+    assert!(script.contains("set -eu\n"), "{}", script);
+    assert!(!script.contains("function ") && !script.contains("&>"), "{}", script);
+
+    // dash is the strictest sh around; fall back to whatever sh there is
+    let sh = if std::path::Path::new("/usr/bin/dash").exists() { "/usr/bin/dash" } else { "/bin/sh" };
+    let run = std::process::Command::new(sh)
+        .arg("-c")
+        .arg(&script)
+        .current_dir(dir.path())
+        .env("PATH", "")
+        .env("ZIBOX_DRY_RUN", "1")
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    assert!(!dir.path().join("pwned").exists(), "{}", script);
+    let stdout = String::from_utf8(run.stdout).unwrap();
+    // Quoted so it could be pasted back into a shell
+    assert!(stdout.contains("Would run: 'echo' 'x'\\''y'\n"), "{}", stdout);
+    for task in &program.blocks[0].tasks {
+        assert!(stdout.contains(&format!("Task Start: {}\n", task.display_name())), "{}", stdout);
+    }
+
+    let script = generate_output(&program, OutputFormat::PowerShell(actions)).unwrap();
+    assert!(script.contains("$ErrorActionPreference = 'Stop'"), "{}", script);
+    assert!(script.contains("Write-Host '[??:??] Starting: $(touch pwned)(x''y)'"), "{}", script);
+    assert!(script.contains("Send-Notification 'Task Start' 'it''s `touch pwned`'"), "{}", script);
+    assert!(script.contains("Send-Notification 'Task Start' '\u{2019}\u{2019}; touch pwned; \u{2018}\u{2018}'"), "{}", script);
+    assert!(script.contains("Invoke-Action 'echo' @('x''y')"), "{}", script);
+
+    // The dry run quotes each word too, where there's a PowerShell to try it
+    let pwsh = std::env::var_os("PATH")
+        .and_then(|path| std::env::split_paths(&path).map(|dir| dir.join("pwsh")).find(|p| p.exists()));
+    if let Some(pwsh) = pwsh {
+        std::fs::write(dir.path().join("plan.ps1"), &script).unwrap();
+        let run = std::process::Command::new(pwsh)
+            .args(["-NoProfile", "-NonInteractive", "-File", "plan.ps1"])
+            .current_dir(dir.path())
+            .env("ZIBOX_DRY_RUN", "1")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(run.stdout).unwrap();
+        assert!(stdout.contains("Would run: 'echo' 'x''y'\n"), "{}", stdout);
+    } else {
+        assert!(script.contains("$_ -replace '([''\\u2018-\\u201B])', '$1$1'"), "{}", script);
+    }
+}
+
+#[test]
+fn test_systemd_and_cron_output() {
+    let mut program = lower("@morning\n  a [1h]\n  b [30m]\n  c [15m]\n  d\n");
+    let start = at_nine();
+    let block = &mut program.blocks[0];
+    block.tasks[0].name = "100% \"$HOME\" it's\nnew".to_string();
+    block.tasks[0] = block.tasks[0].clone().with_scheduled_time(start);
+    block.tasks[1] = block.tasks[1].clone().with_scheduled_time(start + chrono::Duration::minutes(65));
+    block.tasks[2] = block.tasks[2].clone().with_scheduled_time(start + chrono::Duration::minutes(95));
+    block.tasks[2].completed = true;
+    let first = program.blocks[0].tasks[0].id.clone();
+    let second = program.blocks[0].tasks[1].id.clone();
+
+    // A timer and service per scheduled task that isn't done, and the target.
+    // Units an earlier plan generated go; hand-written ones stay.
+    let dir = tempfile::tempdir().unwrap();
+    let generated = "# Generated by Zero Inbox\n[Unit]\n";
+    std::fs::write(dir.path().join("zibox-task_gone.service"), generated).unwrap();
+    std::fs::write(dir.path().join("zibox-task_gone.timer"), generated).unwrap();
+    std::fs::write(dir.path().join("zibox-mine.timer"), "[Unit]\n").unwrap();
+    std::fs::write(dir.path().join("backup.timer"), generated).unwrap();
+    let units = write_systemd_units(&program, dir.path()).unwrap();
+    let mut names: Vec<String> = units.written.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+    names.sort();
+    let mut expected = vec![
+        format!("zibox-{}.service", first),
+        format!("zibox-{}.timer", first),
+        format!("zibox-{}.service", second),
+        format!("zibox-{}.timer", second),
+        "zibox-plan.target".to_string(),
+    ];
+    expected.sort();
+    assert_eq!(names, expected);
+    assert_eq!(units.removed, [dir.path().join("zibox-task_gone.service"), dir.path().join("zibox-task_gone.timer")]);
+    assert!(!dir.path().join("zibox-task_gone.service").exists());
+    assert!(dir.path().join("zibox-mine.timer").exists());
+    assert!(dir.path().join("backup.timer").exists());
+    // Running timers outlive their files, so they're stopped before the reload
+    assert_eq!(
+        units.apply_command(),
+        "systemctl --user stop zibox-task_gone.service zibox-task_gone.timer && systemctl --user daemon-reload \
+         && systemctl --user restart zibox-plan.target"
+    );
+
+    let read = |name: String| std::fs::read_to_string(dir.path().join(name)).unwrap();
+    let timer = read(format!("zibox-{}.timer", second));
+    assert!(timer.contains("OnCalendar=2026-10-16 10:05:00\n"), "{}", timer);
+    assert!(timer.contains("PartOf=zibox-plan.target\n"), "{}", timer);
+    let service = read(format!("zibox-{}.service", first));
+    assert!(service.contains("Description=Zero Inbox: 100%% \"$HOME\" it's new\n"), "{}", service);
+    assert!(service.contains("Type=oneshot\n"), "{}", service);
+    assert!(
+        service.contains("ExecStart=/usr/bin/env notify-send -- \"Task Start\" \"100%% \\\"$$HOME\\\" it's new\"\n"),
+        "{}",
+        service
+    );
+    let target = read("zibox-plan.target".to_string());
+    assert!(target.contains(&format!("Wants=zibox-{}.timer zibox-{}.timer\n", first, second)), "{}", target);
+
+    // On stdout, every unit comes under its file name
+    let units = generate_output(&program, OutputFormat::Systemd).unwrap();
+    assert!(units.contains(&format!("# zibox-{}.timer\n# Generated by Zero Inbox\n[Unit]\n", second)), "{}", units);
+
+    let cron = generate_output(&program, OutputFormat::Cron).unwrap();
+    let lines: Vec<&str> = cron.lines().filter(|l| !l.is_empty() && !l.starts_with('#')).collect();
+    assert_eq!(lines.len(), 2, "{}", cron);
+    assert!(lines[0].starts_with("0 9 16 10 * "), "{}", cron);
+    assert!(lines[0].ends_with(" notify-send -- 'Task Start' '100\\% \"$HOME\" it'\\''s new'"), "{}", cron);
+    assert!(lines[1].starts_with("5 10 16 10 * "), "{}", cron);
+    assert!(cron.contains("\n# 100% \"$HOME\" it's new\n"), "{}", cron);
+}
+
+#[test]
+fn test_template_output() {
+    let mut program = lower("\
+@morning
+  write(report) [90m] p:critical #deepwork #writing
+    outline [30m]
+    draft [1h]
+  email [25m]
+  someday
+");
+    let start = at_nine();
+    let block = &mut program.blocks[0];
+    block.tasks[0] = block.tasks[0].clone().with_scheduled_time(start);
+    block.tasks[1] = block.tasks[1].clone().with_scheduled_time(start + chrono::Duration::minutes(90));
+    block.tasks[1].completed = true;
+
+    let template = "\
+# {{ blocks | length }} block, {{ duration | duration }}
+{# Comment lines and lines with only a tag disappear #}
+{% for block in blocks %}
+## {{ block.name | upper }} from {{ block.start | time }}
+  {% for task in block.tasks %}
+- [{% if task.done %}x{% else %} {% endif %}] {{ task.start | time | default: \"--:--\" }} {{ task.title }}\
+{% if task.tags %} #{{ task.tags | join: \" #\" }}{% endif %}
+    {% for sub in task.subtasks %}
+  {{ loop.index }}. {{ sub.name }} ({{ sub.duration | duration }}){% if not loop.last %};{% endif %}
+    {% endfor %}
+  {% endfor %}
+{% endfor %}
+{% for task in tasks %}{% if task.priority == 'critical' and not task.done %}Focus: {{ task.name }}{% elif task.duration == none %}, {{ task.name }} is open{% endif %}{% endfor %}
+{{ blocks.0 }}";
+    let result = generate_output(&program, OutputFormat::Template(template.to_string()));
+    assert!(
+        result.as_ref().is_err_and(|e| e.to_string() == "Template error on line 13: blocks is a list, which has no field 0"),
+        "{:?}",
+        result
+    );
+
+    let template = template.rsplit_once('\n').unwrap().0;
+    let output = generate_output(&program, OutputFormat::Template(template.to_string())).unwrap();
+    assert_eq!(output, "\
+# 1 block, 1h 55m
+## MORNING from 09:00
+- [ ] 09:00 write(report) #deepwork #writing
+  1. outline (30m);
+  2. draft (1h)
+- [x] 10:30 email
+- [ ] --:-- someday
+Focus: write, someday is open");
+
+    // Mistakes point at their line
+    let error = |template: &str| {
+        generate_output(&program, OutputFormat::Template(template.to_string())).unwrap_err().to_string()
+    };
+    assert_eq!(error("{{ task.name }}"), "Template error on line 1: Unknown variable: task");
+    assert_eq!(error("\n{{ now | shout }}"), "Template error on line 2: Unknown filter: shout");
+    assert_eq!(error("{% for block in blocks %}\n"), "Template error on line 1: {% for %} without {% endfor %}");
+    assert_eq!(error("{% if duration %}{% endfor %}"), "Template error on line 1: Unexpected {% endfor %}");
+    assert_eq!(error("{{ now | time: \"%Q\" }}"), "Template error on line 1: Invalid time format: %Q");
+    assert_eq!(error("{{ blocks | duration }}"), "Template error on line 1: The duration filter needs minutes, not a list");
+    assert_eq!(error("{{ blocks }}"), "Template error on line 1: Can't print an object; pick one of its fields");
+
+    // The format takes its template from the configuration
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("plan.tmpl");
+    std::fs::write(&path, "{{ tasks | length }} tasks").unwrap();
+    let mut config = Config { output_format: "template".to_string(), ..Config::default() };
+    assert!(config.get_output_format().is_err());
+    config.template = Some(path.to_string_lossy().into_owned());
+    let output = generate_output(&program, config.get_output_format().unwrap()).unwrap();
+    assert_eq!(output, "5 tasks");
+}
+
+#[test]
+fn test_chart_rendering() {
+    let config = Config::default();
+    let mut program = lower("\
+@morning
+  write(report) p:critical
+    outline [30m]
+    draft [90m]
+  review [20m] after:write due:2000-01-01
+
+@afternoon
+  plan [45m] after:review due:2099-12-31
+");
+    zero_inbox::schedule_program(&mut program, &config).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+
+    let svg = dir.path().join("schedule.svg");
+    render_chart(&program, &svg).unwrap();
+    let svg = std::fs::read_to_string(svg).unwrap();
+    assert!(svg.starts_with("<svg"), "{}", svg);
+    let labels = [
+        "Zero Inbox Schedule", "write(report)", "› outline", "› draft", "review", "plan", "morning", "afternoon",
+        "due 2000-01-01", "due 2099-12-31",
+    ];
+    for label in labels {
+        assert!(svg.lines().any(|line| line.trim() == label), "missing {}", label);
+    }
+    // One arrowhead per dependency and a diamond per deadline
+    assert_eq!(svg.matches("<polygon").count(), 4, "{}", svg);
+
+    let png = dir.path().join("schedule.png");
+    render_chart(&program, &png).unwrap();
+    assert!(std::fs::read(png).unwrap().starts_with(b"\x89PNG"));
+
+    let error = render_chart(&program, &dir.path().join("schedule.pdf")).unwrap_err();
+    assert!(error.to_string().contains("Unsupported chart format"));
+    let error = render_chart(&lower("@a\n  b [1h]\n"), &dir.path().join("empty.svg")).unwrap_err();
+    assert!(error.to_string().contains("no task is scheduled"));
+}
@@ -0,0 +1,36 @@
+use std::fs;
+use zero_inbox::{formatter, lexer, parser, syntax::SyntaxTree};
+
+#[test]
+fn test_format_canonical_layout() {
+    let source = "# plan\n@morning  # early\n    write(report)   #deepwork [2h] p:HIGH\n        outline [30m]\n    clear(inbox) after:write #b #a\n\n\n\n@evening\nreflect [1h]\n";
+    let expected = "# plan\n@morning  # early\n  write(report) [2h] p:high #deepwork\n    outline [30m]\n  clear(inbox) #a #b after:write\n\n@evening\n  reflect [1h]\n";
+
+    assert_eq!(formatter::format_source(source), expected);
+    assert!(formatter::is_formatted(expected));
+}
+
+#[test]
+fn test_format_preserves_examples() {
+    for entry in fs::read_dir("examples").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "zbx") {
+            continue;
+        }
+        let source = fs::read_to_string(&path).unwrap();
+
+        // The syntax tree is lossless
+        assert_eq!(SyntaxTree::parse(&source).to_string(), source, "{}", path.display());
+
+        // Formatting is idempotent and doesn't change what the file means
+        let formatted = formatter::format_source(&source);
+        assert!(formatter::is_formatted(&formatted), "{}", path.display());
+        let parse = |src: &str| parser::parse(&lexer::tokenize(src)).map_err(|e| e.to_string());
+        assert_eq!(
+            parse(&formatted),
+            parse(&source),
+            "{}",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use std::path::Path;
+use zero_inbox::{
+    ast::Priority,
+    codegen::{generate_output, OutputFormat},
+    config::Config,
+    import::{self, InputFormat},
+};
+
+#[test]
+fn test_json_round_trip() {
+    let config = Config::default();
+    let program = zero_inbox::build_program(Path::new("examples/daily_plan.zbx"), &config).unwrap();
+    let json = generate_output(&program, OutputFormat::Json).unwrap();
+
+    let (imported, _) = import::import(&json, InputFormat::Json, &config).unwrap();
+    assert_eq!(generate_output(&imported, OutputFormat::Json).unwrap(), json);
+}
+
+#[test]
+fn test_json_minimal_plan() {
+    // Version 1 layout, as older releases wrote it
+    let json = r#"{
+        "schema_version": 1,
+        "blocks": [
+            {"name": "morning", "tasks": [
+                {"id": "write", "name": "write", "params": ["report"], "duration": {"minutes": 90}, "priority": "High"},
+                {"id": "review", "name": "review", "depends_on": ["write(report)"],
+                 "subtasks": [{"id": "check", "name": "check", "duration": {"minutes": 15}}]}
+            ]}
+        ]
+    }"#;
+    let (program, _) = import::import(json, InputFormat::Json, &Config::default()).unwrap();
+
+    let review = program.get_task("review").unwrap();
+    assert_eq!(review.depends_on, vec!["write"]);
+    assert_eq!(review.block, "morning");
+    assert_eq!(program.get_task("check").unwrap().block, "morning");
+    // Metadata falls back to the configuration
+    assert_eq!(program.metadata.workday_start.to_string(), "09:00:00");
+}
+
+#[test]
+fn test_json_rejects_bad_plans() {
+    let config = Config::default();
+    let error = |json: &str| import::import(json, InputFormat::Json, &config).unwrap_err().to_string();
+
+    assert!(error(r#"{"blocks": []}"#).contains("Missing schema_version"));
+    assert!(error(r#"{"schema_version": 99, "blocks": []}"#).contains("Unsupported schema_version 99"));
+    assert!(error(r#"{"schema_version": 1}"#).contains("missing field `blocks`"));
+    let duplicate = r#"{"schema_version": 1, "blocks": [{"name": "a", "tasks": [
+        {"id": "x", "name": "one"}, {"id": "x", "name": "two"}]}]}"#;
+    assert!(error(duplicate).contains("Duplicate task id: x"));
+}
+
+#[test]
+fn test_input_format_detection() {
+    assert!(matches!(InputFormat::from_path(Path::new("plan.json")), InputFormat::Json));
+    assert!(matches!(InputFormat::from_path(Path::new("today.md")), InputFormat::Markdown));
+    assert!(matches!(InputFormat::from_path(Path::new("notes/todo.txt")), InputFormat::TodoTxt));
+    assert!(matches!(InputFormat::from_path(Path::new("plan.todotxt")), InputFormat::TodoTxt));
+    assert!(matches!(InputFormat::from_path(Path::new("plan.csv")), InputFormat::Csv { delimiter: b',' }));
+    assert!(matches!(InputFormat::from_path(Path::new("plan.TSV")), InputFormat::Csv { delimiter: b'\t' }));
+    assert!(matches!(InputFormat::from_path(Path::new("plan.zbx")), InputFormat::Zbx));
+    assert!(matches!(InputFormat::from_path(Path::new("plan")), InputFormat::Zbx));
+}
+
+#[test]
+fn test_markdown_checklist() {
+    let markdown = "\
+# Today
+
+Some intro text that isn't a task.
+
+## Morning
+- [ ] Write the report (1h30m) #deepwork !high
+  - [x] outline (30m)
+  - [ ] draft (1h)
+    - keep it short
+    - https://docs.example/draft
+- [ ] clear(inbox) (15m) #admin
+
+## Afternoon
+* [X] call mom (15m) !low
+1. [ ] review(pr) (2h)
+- a plain bullet
+";
+    let (program, _) = import::import(markdown, InputFormat::Markdown, &Config::default()).unwrap();
+
+    let blocks: Vec<&str> = program.blocks.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(blocks, vec!["morning", "afternoon"]);
+
+    let morning = &program.blocks[0].tasks;
+    assert_eq!(morning.len(), 2);
+    let report = &morning[0];
+    assert_eq!(report.name, "write-the-report");
+    assert_eq!(report.duration_minutes(), 90);
+    assert_eq!(report.priority, Priority::High);
+    assert!(report.tags.contains("deepwork"));
+    assert!(!report.completed);
+
+    let subtasks: Vec<(&str, u64, bool)> = report.subtasks.iter()
+        .map(|t| (t.name.as_str(), t.duration_minutes(), t.completed))
+        .collect();
+    assert_eq!(subtasks, vec![("outline", 30, true), ("draft", 60, false)]);
+    assert_eq!(report.subtasks[1].notes, vec!["keep it short"]);
+    assert_eq!(report.subtasks[1].link.as_deref(), Some("https://docs.example/draft"));
+
+    assert_eq!(morning[1].name, "clear");
+    assert_eq!(morning[1].params, vec!["inbox"]);
+
+    let afternoon = &program.blocks[1].tasks;
+    assert_eq!(afternoon.len(), 2);
+    assert_eq!(afternoon[0].name, "call-mom");
+    assert_eq!(afternoon[0].priority, Priority::Low);
+    assert!(afternoon[0].completed);
+    assert_eq!(afternoon[1].duration_minutes(), 120);
+}
+
+#[test]
+fn test_markdown_converts_to_zbx() {
+    let markdown = "## Focus\n- [ ] Plan the week (45m) #planning\n- [ ] email (10m)\n";
+    let config = Config::default();
+    let (program, _) = import::import(markdown, InputFormat::Markdown, &config).unwrap();
+
+    let zbx = generate_output(&program, OutputFormat::Zbx { annotate: false }).unwrap();
+    assert_eq!(zbx, "@focus\n  plan-the-week [45m] #planning\n  email [10m]\n");
+
+    // The converted source compiles to the same tasks
+    let (reparsed, _) = import::import(&zbx, InputFormat::Zbx, &config).unwrap();
+    assert_eq!(
+        generate_output(&reparsed, OutputFormat::Json).unwrap(),
+        generate_output(&program, OutputFormat::Json).unwrap(),
+    );
+}
+
+#[test]
+fn test_todotxt_from_other_tools() {
+    let todo = "\
+(A) 2026-10-01 Call the bank +finance @phone @errands due:2026-10-20
+x 2026-10-02 2026-10-01 Buy milk @errands
+Water plants dur:10m rec:1w https://plants.example/care
+(F) tidy(desk) @home t:2026-10-05
+";
+    let (program, _) = import::import(todo, InputFormat::TodoTxt, &Config::default()).unwrap();
+
+    let blocks: Vec<&str> = program.blocks.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(blocks, vec!["phone", "errands", "default", "home"]);
+
+    let call = &program.blocks[0].tasks[0];
+    assert_eq!(call.name, "call-the-bank");
+    assert_eq!(call.priority, Priority::Critical);
+    let mut tags: Vec<&str> = call.tags.iter().map(|t| t.as_str()).collect();
+    tags.sort();
+    assert_eq!(tags, vec!["errands", "finance"]);
+
+    let milk = &program.blocks[1].tasks[0];
+    assert_eq!(milk.name, "buy-milk");
+    assert!(milk.completed);
+
+    let water = &program.blocks[2].tasks[0];
+    assert_eq!(water.duration_minutes(), 10);
+    assert_eq!(water.link.as_deref(), Some("https://plants.example/care"));
+
+    let tidy = &program.blocks[3].tasks[0];
+    assert_eq!((tidy.name.as_str(), tidy.params.clone()), ("tidy", vec!["desk".to_string()]));
+    assert_eq!(tidy.priority, Priority::Low);
+}
+
+#[test]
+fn test_todotxt_rejects_bad_lines() {
+    let config = Config::default();
+    let error = |todo: &str| import::import(todo, InputFormat::TodoTxt, &config).unwrap_err().to_string();
+
+    assert!(error("(A) +project @context\n").contains("Line 1: task has no description"));
+    assert!(error("a id:x\nb id:x\n").contains("Line 2: duplicate id 'x'"));
+    assert!(error("a\nb parent:later\nc id:later\n").contains("Line 2: unknown parent 'later'"));
+    assert!(error("a dur:soon\n").contains("invalid duration 'soon'"));
+}
+
+#[test]
+fn test_taskwarrior_export_import() {
+    // As `task export` writes it, with an older release's `depends` string
+    let export = r#"[
+        {"id": 1, "description": "Write the report", "entry": "20261015T080000Z",
+         "status": "pending", "project": "work", "priority": "H", "tags": ["deepwork"],
+         "uuid": "11111111-1111-4111-8111-111111111111", "estimate": "PT1H30M", "urgency": 8.2,
+         "annotations": [
+            {"entry": "20261015T080100Z", "description": "keep it short"},
+            {"entry": "20261015T080200Z", "description": "https://docs.example/draft"}
+         ]},
+        {"id": 2, "description": "review", "status": "waiting", "project": "work",
+         "uuid": "22222222-2222-4222-8222-222222222222", "estimate": "1800",
+         "depends": "11111111-1111-4111-8111-111111111111,99999999-9999-4999-8999-999999999999",
+         "due": "20261020T170000Z"},
+        {"id": 0, "description": "call mom", "status": "completed", "priority": "L",
+         "uuid": "33333333-3333-4333-8333-333333333333", "end": "20261014T120000Z"},
+        {"id": 0, "description": "old idea", "status": "deleted",
+         "uuid": "44444444-4444-4444-8444-444444444444"}
+    ]"#;
+    let config = Config::default();
+    let (program, _) = import::import(export, InputFormat::Taskwarrior, &config).unwrap();
+
+    let blocks: Vec<&str> = program.blocks.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(blocks, vec!["work", "default"]);
+
+    let report = program.get_task("11111111-1111-4111-8111-111111111111").unwrap();
+    assert_eq!(report.name, "write-the-report");
+    assert_eq!(report.priority, Priority::High);
+    assert_eq!(report.duration_minutes(), 90);
+    assert!(report.tags.contains("deepwork"));
+    assert_eq!(report.notes, vec!["keep it short"]);
+    assert_eq!(report.link.as_deref(), Some("https://docs.example/draft"));
+
+    // The dependency on a task that wasn't exported is dropped
+    let review = program.get_task("22222222-2222-4222-8222-222222222222").unwrap();
+    assert_eq!(review.depends_on, vec![report.id.clone()]);
+    assert_eq!(review.duration_minutes(), 30);
+    assert!(!review.completed);
+    let due = chrono::DateTime::parse_from_rfc3339("2026-10-20T17:00:00Z").unwrap();
+    assert_eq!(review.due, Some(due.with_timezone(&chrono::Local).date_naive()));
+
+    let call = &program.blocks[1].tasks[0];
+    assert!(call.completed);
+    assert_eq!(call.priority, Priority::Low);
+    assert!(program.get_task("44444444-4444-4444-8444-444444444444").is_none());
+
+    // A `.json` file holding an array is read the same way, and writing it
+    // back keeps every UUID
+    let (from_json, _) = import::import(export, InputFormat::Json, &config).unwrap();
+    let emitted = generate_output(&from_json, OutputFormat::Taskwarrior).unwrap();
+    let tasks: Vec<serde_json::Value> = serde_json::from_str(&emitted).unwrap();
+    let uuids: Vec<&str> = tasks.iter().map(|t| t["uuid"].as_str().unwrap()).collect();
+    assert_eq!(uuids, vec![
+        "11111111-1111-4111-8111-111111111111",
+        "22222222-2222-4222-8222-222222222222",
+        "33333333-3333-4333-8333-333333333333",
+    ]);
+    assert_eq!(tasks[1]["depends"], serde_json::json!(["11111111-1111-4111-8111-111111111111"]));
+    assert_eq!(tasks[2]["status"], "completed");
+}
+
+#[test]
+fn test_taskwarrior_one_task_per_line() {
+    let export = "\
+{\"description\":\"stretch\",\"status\":\"pending\",\"estimate\":\"P1DT2H\",\"uuid\":\"55555555-5555-4555-8555-555555555555\"}
+{\"description\":\"tidy(desk)\",\"status\":\"pending\",\"estimate\":\"20m\"}
+";
+    let (program, _) = import::import(export, InputFormat::Taskwarrior, &Config::default()).unwrap();
+    let tasks = &program.blocks[0].tasks;
+    assert_eq!(tasks[0].duration_minutes(), 26 * 60);
+    assert_eq!((tasks[1].name.as_str(), tasks[1].duration_minutes()), ("tidy", 20));
+
+    let error = import::import(r#"[{"description": "x", "estimate": "soon"}]"#, InputFormat::Taskwarrior, &Config::default())
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("invalid estimate 'soon'"), "{}", error);
+}
+
+#[test]
+fn test_taskwarrior_subtasks() {
+    // A subtask may come before its parent, and a loop of parents is broken
+    let export = r#"[
+        {"description": "outline", "uuid": "a", "zibox_parent": "b", "project": "elsewhere", "status": "completed"},
+        {"description": "write", "uuid": "b", "project": "work", "depends": ["a"]},
+        {"description": "ping", "uuid": "c", "zibox_parent": "d"},
+        {"description": "pong", "uuid": "d", "zibox_parent": "c"}
+    ]"#;
+    let (program, _) = import::import(export, InputFormat::Taskwarrior, &Config::default()).unwrap();
+
+    let write = &program.blocks[0].tasks[0];
+    assert_eq!((program.blocks[0].name.as_str(), write.name.as_str()), ("work", "write"));
+    assert_eq!(write.subtasks[0].name, "outline");
+    assert!(write.subtasks[0].completed && !write.completed);
+    assert!(write.depends_on.is_empty());
+
+    let names: Vec<&str> = program.blocks.iter()
+        .flat_map(|b| b.tasks.iter())
+        .flat_map(|t| t.flatten())
+        .map(|t| t.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["write", "outline", "ping", "pong"]);
+}
+
+#[test]
+fn test_csv_edited_in_a_spreadsheet() {
+    // Columns reordered, one added, IDs and times dropped, a blank row left behind
+    let csv = "\
+Name,Block,Owner,Duration,Priority,Tags,Deps,Completed
+write,morning,sam,1h30m,high,\"deepwork, writing\",,
+review,morning,alex,30,,,write,
+,,,,,,,
+stretch,,,10,low,,,yes
+";
+    let (program, _) = import::import(csv, InputFormat::Csv { delimiter: b',' }, &Config::default()).unwrap();
+
+    let blocks: Vec<&str> = program.blocks.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(blocks, vec!["morning", "default"]);
+
+    let write = &program.blocks[0].tasks[0];
+    assert_eq!(write.duration_minutes(), 90);
+    assert_eq!(write.priority, Priority::High);
+    assert!(write.tags.contains("deepwork") && write.tags.contains("writing"));
+
+    let review = &program.blocks[0].tasks[1];
+    assert_eq!(review.depends_on, vec![write.id.clone()]);
+    assert_eq!(review.priority, Priority::Medium);
+
+    let stretch = &program.blocks[1].tasks[0];
+    assert!(stretch.completed);
+    assert_eq!(stretch.duration_minutes(), 10);
+}
+
+#[test]
+fn test_csv_rejects_bad_rows() {
+    let config = Config::default();
+    let error = |csv: &str| {
+        import::import(csv, InputFormat::Csv { delimiter: b',' }, &config).unwrap_err().to_string()
+    };
+
+    assert!(error("id,name\na,write\n").contains("Missing 'block' column"));
+    assert!(error("block,name,duration\nm,a,soon\n").contains("Row 2: invalid duration 'soon'"));
+    assert!(error("block,name,priority\nm,a,urgent\n").contains("Row 2: invalid priority 'urgent'"));
+    assert!(error("id,block,name\nx,m,a\nx,m,b\n").contains("Row 3: duplicate id 'x'"));
+    assert!(error("id,block,name,parent\nx,m,a,y\ny,m,b,\n").contains("Row 2: unknown parent 'y'"));
+    assert!(error("block,name\nm,\n").contains("Row 2: task has no name"));
+}
@@ -0,0 +1,107 @@
+use serde_json::Value;
+use std::fs;
+use zero_inbox::{
+    codegen::{generate_output, json_schema, OutputFormat, JSON_SCHEMA_VERSION},
+    config::Config,
+    import::{self, InputFormat},
+    ir::IRProgram,
+};
+
+const FIXTURES: &str = "tests/fixtures/json";
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(format!("{}/{}", FIXTURES, name)).unwrap()
+}
+
+/// Emit a plan without its (date-dependent) schedule
+fn emit_unscheduled(mut program: IRProgram) -> String {
+    for block in &mut program.blocks {
+        for task in &mut block.tasks {
+            clear_schedule(task);
+        }
+    }
+    program.metadata.timezone = "+00:00".to_string();
+    generate_output(&program, OutputFormat::Json).unwrap()
+}
+
+fn clear_schedule(task: &mut zero_inbox::ir::IRTask) {
+    task.scheduled_start = None;
+    task.scheduled_end = None;
+    for subtask in &mut task.subtasks {
+        clear_schedule(subtask);
+    }
+}
+
+#[test]
+fn test_json_output_matches_fixture() {
+    let (program, _) = import::import(&fixture("plan.zbx"), InputFormat::Zbx, &Config::default()).unwrap();
+    assert_eq!(emit_unscheduled(program), fixture(&format!("plan.v{}.json", JSON_SCHEMA_VERSION)));
+}
+
+#[test]
+fn test_json_reads_every_version() {
+    let current = fixture(&format!("plan.v{}.json", JSON_SCHEMA_VERSION));
+    for version in 1..=JSON_SCHEMA_VERSION {
+        let json = fixture(&format!("plan.v{}.json", version));
+        let (program, _) = import::import(&json, InputFormat::Json, &Config::default())
+            .unwrap_or_else(|e| panic!("version {}: {}", version, e));
+        assert_eq!(emit_unscheduled(program), current, "version {}", version);
+    }
+}
+
+#[test]
+fn test_json_schema_is_published() {
+    let published: Value = serde_json::from_str(&fs::read_to_string("docs/plan.schema.json").unwrap()).unwrap();
+    assert_eq!(json_schema(), published, "run `zibox schema > docs/plan.schema.json`");
+}
+
+/// Check the parts of the schema our output relies on: required keys are
+/// present and nothing outside `properties` appears
+fn check(value: &Value, schema: &Value, root: &Value, path: &str) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/$defs/");
+        return check(value, &root["$defs"][name], root, path);
+    }
+    if let Some(items) = schema.get("items") {
+        for (i, item) in value.as_array().unwrap().iter().enumerate() {
+            check(item, items, root, &format!("{}[{}]", path, i));
+        }
+    }
+    if let Some(properties) = schema.get("properties") {
+        let object = value.as_object().unwrap_or_else(|| panic!("{} is not an object", path));
+        for required in schema["required"].as_array().into_iter().flatten() {
+            assert!(object.contains_key(required.as_str().unwrap()), "{} lacks {}", path, required);
+        }
+        for (key, child) in object {
+            let property = properties.get(key).unwrap_or_else(|| panic!("{}.{} is not in the schema", path, key));
+            check(child, property, root, &format!("{}.{}", path, key));
+        }
+    }
+    if let Some(options) = schema.get("enum") {
+        assert!(options.as_array().unwrap().contains(value), "{} = {}", path, value);
+    }
+}
+
+#[test]
+fn test_json_output_follows_schema() {
+    let schema = json_schema();
+    let output: Value = serde_json::from_str(&fixture(&format!("plan.v{}.json", JSON_SCHEMA_VERSION))).unwrap();
+    check(&output, &schema, &schema, "plan");
+    assert_eq!(output["schema_version"], schema["properties"]["schema_version"]["const"]);
+}
+
+#[test]
+fn test_json_keeps_due_dates_and_commands() {
+    let source = "@work\n  open(notes) [30m] due:2026-10-20 run:\"code ~/notes.md\"\n";
+    let (program, _) = import::import(source, InputFormat::Zbx, &Config::default()).unwrap();
+    let json = emit_unscheduled(program);
+    let output: Value = serde_json::from_str(&json).unwrap();
+    let task = &output["blocks"][0]["tasks"][0];
+    assert_eq!(task["due"], "2026-10-20");
+    assert_eq!(task["run"], "code ~/notes.md");
+
+    let schema = json_schema();
+    check(&output, &schema, &schema, "plan");
+    let (reread, _) = import::import(&json, InputFormat::Json, &Config::default()).unwrap();
+    assert_eq!(emit_unscheduled(reread), json);
+}
@@ -0,0 +1,49 @@
+use zero_inbox::{config::Config, lint::{self, Severity}};
+
+#[test]
+fn test_lint_rules() {
+    let source = "@morning\n  write(report) [2h] after:review\n  clear(inbox)\n  clear(inbox) [5m]\n@afternoon\n  review [1h]\n";
+    let diagnostics = lint::check_source(source, &Config::default()).unwrap();
+    let found: Vec<_> = diagnostics.iter().map(|d| (d.rule, d.line)).collect();
+
+    assert_eq!(found, vec![("ZB005", Some(2)), ("ZB001", Some(3)), ("ZB003", Some(4))]);
+    assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+
+    // Syntax errors carry their line
+    let diagnostics = lint::check_source("@morning\n  a [1h]\n  b [soon]\n", &Config::default()).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].rule, diagnostics[0].line), ("ZB000", Some(3)));
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+
+    // An unknown priority doesn't stop the other rules
+    let diagnostics = lint::check_source("@morning\n  clear(inbox)\n  a [1h] p:bogus\n", &Config::default()).unwrap();
+    let found: Vec<_> = diagnostics.iter().map(|d| (d.rule, d.line)).collect();
+    assert_eq!(found, vec![("ZB001", Some(2)), ("ZB004", Some(3))]);
+}
+
+#[test]
+fn test_lint_severity_overrides() {
+    let source = "@morning\n  clear(inbox)\n  a [1h] p:bogus\n";
+    let mut config = Config::default();
+    config.lint.insert("missing-duration".to_string(), "error".to_string());
+    config.lint.insert("ZB004".to_string(), "off".to_string());
+
+    // The compiler rejects the priority whether or not ZB004 is on, and the
+    // task without a duration is still found
+    let diagnostics = lint::check_source(source, &config).unwrap();
+    let found: Vec<_> = diagnostics.iter().map(|d| (d.rule, d.severity, d.line)).collect();
+    assert_eq!(
+        found,
+        [("ZB001", Severity::Error, Some(2)), ("ZB000", Severity::Error, Some(3))],
+        "{:?}",
+        diagnostics
+    );
+
+    let source = "@morning\n  clear(inbox)\n";
+    let diagnostics = lint::check_source(source, &config).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+
+    config.lint.insert("no-such-rule".to_string(), "warn".to_string());
+    assert!(lint::check_source(source, &config).is_err());
+}
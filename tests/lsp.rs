@@ -0,0 +1,101 @@
+use lsp_types::{CompletionTextEdit, DiagnosticSeverity, HoverContents, NumberOrString, Position, Range};
+use zero_inbox::{config::Config, lsp};
+
+const SOURCE: &str = "@morning\n  write(report) [2h] #deepwork id:writeup\n  review [30m] p:high after:writeup\n  clear(inbox)\n";
+
+#[test]
+fn test_lsp_diagnostics() {
+    let diagnostics = lsp::diagnostics(SOURCE, &Config::default());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, Some(NumberOrString::String("ZB001".to_string())));
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(diagnostics[0].range, Range::new(Position::new(3, 2), Position::new(3, 14)));
+}
+
+#[test]
+fn test_lsp_completions() {
+    let config = Config::default();
+    let labels = |line: &str, character: u32| {
+        let source = format!("@morning\n  write(report) [2h] #deepwork id:writeup\n{}\n", line);
+        lsp::completions(&source, Position::new(2, character), &config)
+            .into_iter()
+            .map(|item| item.label)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(labels("  review p:h", 12), vec!["high"]);
+    assert_eq!(labels("  review #d", 11), vec!["deepwork"]);
+    assert_eq!(labels("  review after:x,wr", 19), vec!["write", "write(report)", "writeup"]);
+    assert!(labels("  review [30m]", 14).is_empty());
+
+    // Only the part after the `:` is replaced
+    let items = lsp::completions("a [1h]\nb p:cr\n", Position::new(1, 6), &config);
+    match &items[0].text_edit {
+        Some(CompletionTextEdit::Edit(edit)) => {
+            assert_eq!(edit.range, Range::new(Position::new(1, 4), Position::new(1, 6)));
+            assert_eq!(edit.new_text, "critical");
+        },
+        other => panic!("unexpected edit: {:?}", other),
+    }
+}
+
+#[test]
+fn test_lsp_hover_and_definition() {
+    let hover = lsp::hover(SOURCE, Position::new(1, 4), &Config::default()).unwrap();
+    let HoverContents::Markup(content) = hover.contents else {
+        panic!("expected markdown");
+    };
+    assert!(content.value.contains("**write(report)**"), "{}", content.value);
+    assert!(content.value.contains("(2h)"), "{}", content.value);
+    assert!(content.value.contains("#deepwork"), "{}", content.value);
+    assert!(lsp::hover(SOURCE, Position::new(0, 1), &Config::default()).is_none());
+
+    // Jump from `after:writeup` to the labelled task
+    let target = lsp::definition(SOURCE, Position::new(2, 30)).unwrap();
+    assert_eq!(target.start.line, 1);
+    assert!(lsp::definition(SOURCE, Position::new(2, 4)).is_none());
+}
+
+#[test]
+fn test_lsp_formatting() {
+    assert!(lsp::formatting(SOURCE).is_empty());
+
+    let edits = lsp::formatting("@morning\n    a [1h]\n");
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].range, Range::new(Position::new(0, 0), Position::new(2, 0)));
+    assert_eq!(edits[0].new_text, "@morning\n  a [1h]\n");
+}
+
+#[test]
+fn test_lsp_survives_malformed_notifications() {
+    use lsp_server::{Connection, Message, Notification, Request, RequestId};
+    use serde_json::json;
+
+    let (server, client) = Connection::memory();
+    let serving = std::thread::spawn(move || lsp::serve(server, Config::default()));
+    let request = |id: i32, method: &str, params| {
+        client.sender.send(Message::Request(Request::new(RequestId::from(id), method.to_string(), params))).unwrap();
+    };
+    let notify = |method: &str, params| {
+        client.sender.send(Message::Notification(Notification::new(method.to_string(), params))).unwrap();
+    };
+
+    request(1, "initialize", json!({ "capabilities": {} }));
+    assert!(matches!(client.receiver.recv().unwrap(), Message::Response(_)));
+    notify("initialized", json!({}));
+
+    // A change with no document is dropped, and the server carries on
+    notify("textDocument/didChange", json!({ "contentChanges": "nope" }));
+    notify("textDocument/didOpen", json!({
+        "textDocument": { "uri": "file:///plan.zbx", "languageId": "zbx", "version": 1, "text": SOURCE }
+    }));
+    match client.receiver.recv().unwrap() {
+        Message::Notification(published) => assert_eq!(published.method, "textDocument/publishDiagnostics"),
+        other => panic!("expected diagnostics, got {:?}", other),
+    }
+
+    request(2, "shutdown", json!(null));
+    assert!(matches!(client.receiver.recv().unwrap(), Message::Response(_)));
+    notify("exit", json!(null));
+    serving.join().unwrap().unwrap();
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use zero_inbox::{actions::Actions, config::Config, ir, lexer, parser, syntax};
+
+#[test]
+fn test_variables_and_settings() {
+    let source = "let focus = 90m\nlet start = 08:30\nset workday_start = start\n\n@morning\n  write(report) [focus] #deepwork\n";
+    let program = parser::parse_program(&lexer::tokenize(source)).unwrap();
+
+    let task = &program.blocks[0].tasks[0];
+    assert_eq!(task.duration.as_ref().map(|d| d.minutes), Some(90));
+
+    let mut config = Config::default();
+    config.apply_settings(&program.settings).unwrap();
+    assert_eq!(config.workday_start, "08:30");
+
+    let undefined = parser::parse_program(&lexer::tokenize("@morning\n  write(report) [focus]\n"));
+    assert!(matches!(undefined, Err(parser::ParseError::UndefinedVariable(_))));
+}
+
+#[test]
+fn test_tasks_named_like_keywords() {
+    let source = "let focus = 90m\n@morning\n  set up laptop [30m]\n  let go [focus] #calm\n  settle(in) [5m]\n";
+    let program = parser::parse_program(&lexer::tokenize(source)).unwrap();
+    assert!(program.settings.is_empty());
+
+    let tasks = &program.blocks[0].tasks;
+    let names: Vec<&str> = tasks.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["set", "let", "settle"]);
+    assert_eq!(tasks[0].duration.as_ref().map(|d| d.minutes), Some(30));
+    assert_eq!(tasks[1].duration.as_ref().map(|d| d.minutes), Some(90));
+    assert!(tasks[1].has_tag("calm"));
+}
+
+#[test]
+fn test_indented_subtasks() {
+    let source = "@morning\n  write(report)\n    outline [30m] #deepwork\n    draft [1h]\n  clear(inbox) [15m]\n";
+    let blocks = parser::parse(&lexer::tokenize(source)).unwrap();
+
+    let tasks = &blocks[0].tasks;
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].subtasks.len(), 2);
+    assert!(tasks[0].duration.is_none());
+    assert_eq!(tasks[0].effective_duration().map(|d| d.minutes), Some(90));
+
+    let program = zero_inbox::ir::to_ir(&blocks, Default::default());
+    assert_eq!(program.blocks[0].tasks[0].duration_minutes(), 90);
+    assert_eq!(program.blocks[0].tasks[0].subtasks[1].name, "draft");
+}
+
+#[test]
+fn test_notes_and_links() {
+    let source = "@work\n  fix(bug) [1h] link:https://tracker.example/T-42\n    > repro in staging first\n    \"\"\"\n    Stack trace is in the ticket.\n    Ping ops when done.\n    \"\"\"\n  review(prs) [30m]\n";
+    let blocks = parser::parse(&lexer::tokenize(source)).unwrap();
+
+    let task = &blocks[0].tasks[0];
+    assert_eq!(task.link.as_deref(), Some("https://tracker.example/T-42"));
+    assert_eq!(task.notes, vec![
+        "repro in staging first".to_string(),
+        "Stack trace is in the ticket.\nPing ops when done.".to_string(),
+    ]);
+    assert_eq!(blocks[0].tasks[1].name, "review");
+
+    let dangling = parser::parse(&lexer::tokenize("> orphan note\n"));
+    assert!(matches!(dangling, Err(parser::ParseError::DanglingNote(_))));
+}
+
+#[test]
+fn test_due_dates() {
+    let blocks = parser::parse(&lexer::tokenize("@work\n  file(taxes) [2h] due:2026-10-31\n")).unwrap();
+    assert_eq!(blocks[0].tasks[0].due, chrono::NaiveDate::from_ymd_opt(2026, 10, 31));
+
+    let invalid = parser::parse(&lexer::tokenize("@work\n  file(taxes) due:friday\n"));
+    assert!(matches!(invalid, Err(parser::ParseError::InvalidDate(_))));
+}
+
+#[test]
+fn test_stable_task_ids() {
+    let before = "@morning\n  write(report) [2h] id:writeup\n  review(prs) [30m]\n  code(feature) [1h] after:writeup,review\n";
+    let after = "@morning\n  clear(inbox) [15m]\n  write(report) [2h] id:writeup\n  review(prs) [30m]\n  code(feature) [1h] after:writeup,review\n";
+
+    let ids = |source: &str| {
+        let blocks = parser::parse(&lexer::tokenize(source)).unwrap();
+        let program = zero_inbox::ir::to_ir(&blocks, Default::default());
+        program.blocks[0].tasks.iter()
+            .map(|t| (t.name.clone(), t.id.clone(), t.depends_on.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let before = ids(before);
+    let after = ids(after);
+    assert_eq!(before[0].1, "writeup");
+    assert_eq!(before[2].2, vec!["writeup".to_string(), before[1].1.clone()]);
+    // Inserting a line doesn't change any existing IDs
+    assert_eq!(&after[1..], &before[..]);
+
+    let duplicate = parser::parse(&lexer::tokenize("a id:x\nb id:x\n"));
+    assert!(matches!(duplicate, Err(parser::ParseError::DuplicateLabel(_))));
+}
+
+#[test]
+fn test_run_commands() {
+    let source = "@work\n  write(report) [1h] run:\"code ~/notes/report # draft.md\" #deepwork  # editor\n  timer run:termdown\n  odd run:\"say \\\"hi\\\" \\\\o/\"\n";
+    let tree = syntax::SyntaxTree::parse(source);
+    assert_eq!(tree.nodes[1].comment.as_deref(), Some("editor"));
+
+    let blocks = parser::parse(&lexer::tokenize(source)).unwrap();
+    let tasks = &blocks[0].tasks;
+    // Quotes keep the command one attribute, `#` and all
+    assert_eq!(tasks[0].run.as_deref(), Some("code ~/notes/report # draft.md"));
+    assert!(tasks[0].tags.contains("deepwork"));
+    assert_eq!(tasks[1].run.as_deref(), Some("termdown"));
+    assert_eq!(tasks[2].run.as_deref(), Some("say \"hi\" \\o/"));
+    for task in tasks {
+        let run = task.run.as_deref().unwrap();
+        assert_eq!(syntax::unquote(&syntax::quote(run)), run);
+    }
+
+    // Quotes only group a `run:` command; elsewhere they're plain characters
+    let plain = parser::parse(&lexer::tokenize("@work\n  call \"bob [30m] #work  # ask first\n  say\"hi\" [5m]\n")).unwrap();
+    let plain = &plain[0].tasks;
+    assert_eq!(plain[0].duration.as_ref().map(|d| d.minutes), Some(30));
+    assert!(plain[0].tags.contains("work"));
+    assert_eq!((plain[1].name.as_str(), plain[1].duration.as_ref().map(|d| d.minutes)), ("say\"hi\"", Some(5)));
+
+    let actions = Actions {
+        allow: vec!["code".to_string(), "xdg-open".to_string()],
+        commands: HashMap::from([("open".to_string(), "xdg-open {1}".to_string())]),
+    };
+    let program = ir::to_ir(&blocks, Default::default());
+    let home = dirs::home_dir().unwrap();
+    let command = actions.command(&program.blocks[0].tasks[0]).unwrap().unwrap();
+    assert_eq!(command, ["code".to_string(), home.join("notes/report").to_string_lossy().into_owned(), "#".to_string(), "draft.md".to_string()]);
+    // Not on the allow-list
+    let refused = actions.command(&program.blocks[0].tasks[1]).unwrap().unwrap_err();
+    assert!(refused.to_string().contains("termdown is not in the allow-list"), "{}", refused);
+    // An allowed name doesn't let a program of that name run from anywhere
+    for run in ["/tmp/zrev/evil/code --x", "./code", "bin/code"] {
+        let mut task = program.blocks[0].tasks[0].clone();
+        task.run = Some(run.to_string());
+        let refused = actions.command(&task).unwrap().unwrap_err();
+        assert!(refused.to_string().contains("is not in the allow-list"), "{}", refused);
+    }
+
+    // Commands from the config, with parameters filled in as single words
+    let blocks = parser::parse(&lexer::tokenize("@web\n  open(https://example.com/?q=a;b&c=$HOME)\n  open\n  read\n")).unwrap();
+    let program = ir::to_ir(&blocks, Default::default());
+    let tasks = &program.blocks[0].tasks;
+    assert_eq!(actions.command(&tasks[0]).unwrap().unwrap(), ["xdg-open", "https://example.com/?q=a;b&c=$HOME"]);
+    assert!(actions.command(&tasks[1]).unwrap().is_err());
+    assert!(actions.command(&tasks[2]).is_none());
+}
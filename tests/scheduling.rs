@@ -16,3 +16,18 @@ fn test_schedule_respects_time_windows() {
         }
     }
 }
+
+#[test]
+fn test_tasks_without_duration_stay_unscheduled() {
+    let ast = parse_file(std::path::Path::new("examples/daily_plan.zbx")).unwrap();
+    let metadata = Config::default().to_ir_metadata().unwrap();
+    let mut ir = ir::to_ir(&ast, metadata);
+    let sched = scheduler::create_scheduler(scheduler::ScheduleMode::Naive, None);
+    sched.schedule(&mut ir);
+    let tasks: Vec<_> = ir.blocks.iter().flat_map(|b| b.tasks.iter()).collect();
+    let clear = tasks.iter().find(|t| t.name == "clear").unwrap();
+    assert_eq!(clear.scheduled_start, None);
+    let write = tasks.iter().find(|t| t.name == "write").unwrap();
+    let coffee = tasks.iter().find(|t| t.name == "drink").unwrap();
+    assert_eq!(write.scheduled_end, coffee.scheduled_start);
+}